@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+/*
+    Values given to the variables of a formula, looked up by name while a parsed tree is evaluated.
+    The values are kept next to each other so the sets algebra can use them as its universe,
+    and set overwrites in place, so one assignment can be reused for every row of a truth table.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment<T> {
+    slots: HashMap<String, usize>,
+    values: Vec<T>,
+}
+
+impl<T> Assignment<T> {
+    pub fn new() -> Self {
+        Assignment {
+            slots: HashMap::new(),
+            values: Vec::new(),
+        }
+    }
+
+    // Binds name to value, replacing the previous value if it was already bound
+    pub fn set(&mut self, name: &str, value: T) {
+        match self.slots.get(name) {
+            Some(&slot) => self.values[slot] = value,
+            None => {
+                self.slots.insert(name.to_string(), self.values.len());
+                self.values.push(value);
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.slots.get(name).map(|&slot| &self.values[slot])
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.slots.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.keys().map(|name| name.as_str())
+    }
+
+    // Every bound value, in the order they were first set
+    pub fn values(&self) -> &Vec<T> {
+        &self.values
+    }
+
+    // Names with their values, in the order they were first set
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        let mut names: Vec<(&str, usize)> = self
+            .slots
+            .iter()
+            .map(|(name, &slot)| (name.as_str(), slot))
+            .collect();
+        names.sort_by_key(|&(_, slot)| slot);
+        names
+            .into_iter()
+            .map(move |(name, slot)| (name, &self.values[slot]))
+    }
+}
+
+impl<T> Default for Assignment<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<(String, T)> for Assignment<T> {
+    fn from_iter<I: IntoIterator<Item = (String, T)>>(iter: I) -> Self {
+        let mut assignment: Assignment<T> = Assignment::new();
+        for (name, value) in iter {
+            assignment.set(&name, value);
+        }
+        assignment
+    }
+}
+
+impl<T> From<HashMap<String, T>> for Assignment<T> {
+    fn from(values: HashMap<String, T>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T: Clone> From<&HashMap<String, T>> for Assignment<T> {
+    fn from(values: &HashMap<String, T>) -> Self {
+        values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_get() {
+        let mut assignment: Assignment<bool> = Assignment::new();
+        assert!(assignment.is_empty());
+        assignment.set("A", true);
+        assignment.set("reset_n", false);
+        assert_eq!(assignment.get("A"), Some(&true));
+        assert_eq!(assignment.get("B"), None);
+
+        // Setting again overwrites the value in its slot
+        assignment.set("A", false);
+        assert_eq!(assignment.len(), 2);
+        assert_eq!(assignment.values(), &vec![false, false]);
+        assert!(assignment.contains("reset_n"));
+        assert_eq!(
+            assignment.iter().collect::<Vec<(&str, &bool)>>(),
+            vec![("A", &false), ("reset_n", &false)]
+        );
+
+        let hash: HashMap<String, bool> = HashMap::from([("A".to_string(), false)]);
+        let from_hash: Assignment<bool> = Assignment::from(&hash);
+        assert_eq!(from_hash.get("A"), Some(&false));
+        assert_eq!(from_hash.names().collect::<Vec<&str>>(), vec!["A"]);
+    }
+}
@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul};
+
+/*
+    Unsigned integer of any size, for results like model counts that overflow u64.
+    Stored as base 2^32 digits, least significant first, without leading zeros,
+    so zero has no digits and two equal numbers have the same digits.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { digits: Vec::new() }
+    }
+
+    pub fn one() -> Self {
+        BigUint::from(1u64)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    // 2^exponent
+    pub fn pow2(exponent: usize) -> Self {
+        let mut digits: Vec<u32> = vec![0; exponent / 32];
+        digits.push(1 << (exponent % 32));
+        BigUint { digits }
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits.len() {
+            0 => Some(0),
+            1 => Some(self.digits[0] as u64),
+            2 => Some(self.digits[0] as u64 | (self.digits[1] as u64) << 32),
+            _ => None,
+        }
+    }
+
+    // Number of bits needed to write it, 0 for zero
+    pub fn bits(&self) -> usize {
+        match self.digits.last() {
+            None => 0,
+            Some(last) => 32 * self.digits.len() - last.leading_zeros() as usize,
+        }
+    }
+
+    fn trim(mut self) -> Self {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        self
+    }
+
+    // Divides in place by a single digit and returns the remainder
+    fn divide_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder: u64 = 0;
+        for digit in self.digits.iter_mut().rev() {
+            let current: u64 = remainder << 32 | *digit as u64;
+            *digit = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        remainder as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        BigUint {
+            digits: vec![value as u32, (value >> 32) as u32],
+        }
+        .trim()
+    }
+}
+
+impl Add<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let mut digits: Vec<u32> =
+            Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut carry: u64 = 0;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum: u64 = carry
+                + *self.digits.get(i).unwrap_or(&0) as u64
+                + *other.digits.get(i).unwrap_or(&0) as u64;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        BigUint { digits }.trim()
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        &self + &other
+    }
+}
+
+impl Mul<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+        let mut digits: Vec<u32> = vec![0; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry: u64 = 0;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let current: u64 = digits[i + j] as u64 + a as u64 * b as u64 + carry;
+                digits[i + j] = current as u32;
+                carry = current >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigUint { digits }.trim()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        &self * &other
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Nine decimal digits at a time, most significant group last
+        let mut rest: BigUint = self.clone();
+        let mut groups: Vec<u32> = Vec::new();
+        while !rest.is_zero() {
+            groups.push(rest.divide_small(1_000_000_000));
+        }
+        let mut decimal: String = groups.last().unwrap().to_string();
+        for group in groups.iter().rev().skip(1) {
+            decimal.push_str(&format!("{:09}", group));
+        }
+        f.pad(&decimal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let a: BigUint = BigUint::from(u64::MAX);
+        let sum: BigUint = &a + &BigUint::one();
+        assert_eq!(sum, BigUint::pow2(64));
+        assert_eq!(sum.to_u64(), None);
+        assert_eq!(sum.bits(), 65);
+        assert_eq!(
+            (&a * &a).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!(
+            BigUint::pow2(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(BigUint::from(1_000_000_000).to_string(), "1000000000");
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert!(BigUint::zero() * a.clone() == BigUint::zero());
+
+        // 3^50 by repeated multiplication
+        let three: BigUint = BigUint::from(3);
+        let power: BigUint = (0..50).fold(BigUint::one(), |power, _| &power * &three);
+        assert_eq!(power.to_string(), "717897987691852588770249");
+        assert!(power > a && a > BigUint::from(7));
+    }
+}
@@ -1,5 +1,28 @@
-use regex::Regex;
+use crate::aux::lexer::{tokenize, Lexer, Token};
 
+// True when the formula is made only of variables and operators, without 0/1 constants
 pub fn check_only_vars(input: &str) -> bool {
-    Regex::new(r"^[A-Z!&|ˆ>=]+$").unwrap().is_match(input)
+    match tokenize(input) {
+        Ok(tokens) => {
+            !tokens.is_empty()
+                && tokens
+                    .iter()
+                    .all(|lexeme| matches!(lexeme.token, Token::Var(_) | Token::Operator(_)))
+        }
+        Err(_) => false,
+    }
+}
+
+// Sorted, deduplicated names of the variables in a formula, single letters and identifiers alike
+pub fn formula_variables(input: &str) -> Vec<String> {
+    let mut variables: Vec<String> = Lexer::new(input)
+        .map_while(Result::ok)
+        .filter_map(|lexeme| match lexeme.token {
+            Token::Var(name) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect();
+    variables.sort();
+    variables.dedup();
+    variables
 }
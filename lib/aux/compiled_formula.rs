@@ -0,0 +1,290 @@
+use crate::aux::{Assignment, ParseError};
+
+// One step of a compiled formula, run on a stack of values
+#[derive(Debug, Clone)]
+pub enum Instruction<T> {
+    // Pushes the value of the variable in the given slot
+    Load(usize),
+    Const(T),
+    // The operator symbol and the function that evaluates it
+    Unary(char, fn(&T, Option<&Vec<T>>) -> T),
+    Binary(char, fn(&T, &T, Option<&Vec<T>>) -> T),
+}
+
+/*
+    A formula lowered from an ExprNode into a flat list of instructions in RPN order.
+    Variables are numbered by slot, in the sorted order of their names, and the operators
+    are the functions of the evaluator that compiled it, registered connectives included.
+    The slots and the stack are allocated once, so evaluating a bool formula allocates nothing.
+*/
+#[derive(Debug, Clone)]
+pub struct CompiledFormula<T> {
+    instructions: Vec<Instruction<T>>,
+    variables: Vec<String>,
+    values: Vec<T>,
+    stack: Vec<T>,
+    lane_stack: Vec<u64>,
+}
+
+impl<T: Clone> CompiledFormula<T> {
+    pub(crate) fn new(
+        instructions: Vec<Instruction<T>>,
+        variables: Vec<String>,
+        depth: usize,
+    ) -> Self {
+        let values: Vec<T> = Vec::with_capacity(variables.len());
+        CompiledFormula {
+            instructions,
+            variables,
+            values,
+            stack: Vec::with_capacity(depth),
+            lane_stack: Vec::with_capacity(depth),
+        }
+    }
+
+    pub fn instructions(&self) -> &[Instruction<T>] {
+        &self.instructions
+    }
+
+    // Names of the variables, the value of variables()[i] goes in slot i
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.variables.iter().position(|var| var == name)
+    }
+
+    /*
+        Evaluates the formula with values[i] as the value of slot i.
+        The values are also the universe of the sets algebra.
+        Panics if there isn't exactly one value per variable, checked_evaluate_with doesn't.
+    */
+    pub fn evaluate_with(&mut self, values: &[T]) -> T {
+        self.checked_evaluate_with(values)
+            .expect("Expected one value per variable")
+    }
+
+    // evaluate_with, None if there isn't exactly one value per variable
+    pub fn checked_evaluate_with(&mut self, values: &[T]) -> Option<T> {
+        if values.len() != self.variables.len() {
+            return None;
+        }
+        self.values.clear();
+        self.values.extend_from_slice(values);
+        Some(self.run())
+    }
+
+    pub fn evaluate_assignment(&mut self, assignment: &Assignment<T>) -> Result<T, ParseError> {
+        self.values.clear();
+        for name in &self.variables {
+            match assignment.get(name) {
+                Some(value) => self.values.push(value.clone()),
+                None => {
+                    return Err(ParseError::UnboundVariable {
+                        name: name.clone(),
+                        position: None,
+                    })
+                }
+            }
+        }
+        Ok(self.run())
+    }
+
+    fn run(&mut self) -> T {
+        let universal: Option<&Vec<T>> = Some(&self.values);
+        self.stack.clear();
+        for instruction in &self.instructions {
+            let value: T = match instruction {
+                Instruction::Load(slot) => self.values[*slot].clone(),
+                Instruction::Const(value) => value.clone(),
+                Instruction::Unary(_, func) => {
+                    let a: T = self.stack.pop().unwrap();
+                    func(&a, universal)
+                }
+                Instruction::Binary(_, func) => {
+                    let b: T = self.stack.pop().unwrap();
+                    let a: T = self.stack.pop().unwrap();
+                    func(&a, &b, universal)
+                }
+            };
+            self.stack.push(value);
+        }
+        // The tree it was compiled from always leaves exactly one value
+        self.stack.pop().unwrap()
+    }
+}
+
+impl CompiledFormula<bool> {
+    /*
+        Evaluates 64 assignments at once. Bit i of lanes[slot] is the value of that slot
+        in assignment i, and bit i of the result is the value of the formula for it.
+        The core operators run on whole words, registered connectives bit by bit.
+        Panics if there isn't exactly one lane per variable, checked_evaluate_lanes doesn't.
+    */
+    pub fn evaluate_lanes(&mut self, lanes: &[u64]) -> u64 {
+        self.checked_evaluate_lanes(lanes)
+            .expect("Expected one lane per variable")
+    }
+
+    // evaluate_lanes, None if there isn't exactly one lane per variable
+    pub fn checked_evaluate_lanes(&mut self, lanes: &[u64]) -> Option<u64> {
+        if lanes.len() != self.variables.len() {
+            return None;
+        }
+        self.lane_stack.clear();
+        for instruction in &self.instructions {
+            let word: u64 = match instruction {
+                Instruction::Load(slot) => lanes[*slot],
+                Instruction::Const(value) => {
+                    if *value {
+                        u64::MAX
+                    } else {
+                        0
+                    }
+                }
+                Instruction::Unary(op, func) => {
+                    let a: u64 = self.lane_stack.pop().unwrap();
+                    match op {
+                        '!' => !a,
+                        _ => bitwise(|i| func(&bit(a, i), None)),
+                    }
+                }
+                Instruction::Binary(op, func) => {
+                    let b: u64 = self.lane_stack.pop().unwrap();
+                    let a: u64 = self.lane_stack.pop().unwrap();
+                    match op {
+                        '&' => a & b,
+                        '|' => a | b,
+                        '^' => a ^ b,
+                        '>' => !a | b,
+                        '=' => !(a ^ b),
+                        _ => bitwise(|i| func(&bit(a, i), &bit(b, i), None)),
+                    }
+                }
+            };
+            self.lane_stack.push(word);
+        }
+        self.lane_stack.pop()
+    }
+}
+
+fn bit(word: u64, i: u32) -> bool {
+    (word >> i) & 1 == 1
+}
+
+// Builds a word from the value of each of its 64 bits
+fn bitwise(value: impl Fn(u32) -> bool) -> u64 {
+    (0..64)
+        .filter(|&i| value(i))
+        .fold(0, |word, i| word | (1 << i))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aux::{Assignment, ExpressionEvaluator, ParseError};
+    use crate::boolean_operations::BooleanOperations;
+    use crate::sets::eval_set::eval_set::SetOperations;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_matches_tree() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        let formulas = ["AB&C!|D>", "AB^C=", "AB↑C↓", "A1&B0|^", "x1 reset_n>A="];
+        for formula in formulas {
+            let tree = evaluator.parse(formula).unwrap();
+            let mut compiled = evaluator.compile(&tree);
+            let variables: Vec<String> = compiled.variables().to_vec();
+            for i in 0..1usize << variables.len() {
+                let values: Vec<bool> = (0..variables.len()).map(|j| (i >> j) & 1 == 1).collect();
+                let assignment: Assignment<bool> =
+                    variables.iter().cloned().zip(values.clone()).collect();
+                assert_eq!(
+                    compiled.evaluate_with(&values),
+                    evaluator.evaluate_assignment(&tree, &assignment).unwrap(),
+                    "{} {:?}",
+                    formula,
+                    values
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lanes() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        // Every combination of three variables in the low 8 bits
+        let lanes: [u64; 3] = [0xF0, 0xCC, 0xAA];
+        for formula in ["AB&C!|", "AB>C=", "AB^1&", "AB↑C⊙", "AB←0|"] {
+            let mut compiled = evaluator.compile_formula(formula).unwrap();
+            let lanes: &[u64] = &lanes[..compiled.variables().len()];
+            let word: u64 = compiled.evaluate_lanes(lanes);
+            for i in 0..8 {
+                let values: Vec<bool> = lanes.iter().map(|lane| (lane >> i) & 1 == 1).collect();
+                assert_eq!(
+                    (word >> i) & 1 == 1,
+                    compiled.evaluate_with(&values),
+                    "{} {:?}",
+                    formula,
+                    values
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_slots() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let mut compiled = evaluator.compile_formula("C A&B|A!&").unwrap();
+        assert_eq!(compiled.variables(), ["A", "B", "C"]);
+        assert_eq!(compiled.slot("C"), Some(2));
+        assert_eq!(compiled.instructions().len(), 8);
+        assert!(compiled.evaluate_with(&[false, true, false]));
+        assert!(!compiled.evaluate_with(&[true, true, true]));
+        assert_eq!(compiled.checked_evaluate_with(&[true, true]), None);
+        assert_eq!(
+            compiled.checked_evaluate_with(&[true, true, true]),
+            Some(false)
+        );
+        assert_eq!(compiled.checked_evaluate_lanes(&[0b01, 0b11]), None);
+        assert_eq!(
+            compiled.checked_evaluate_lanes(&[0b01, 0b11, 0b10]),
+            Some(0b10)
+        );
+
+        let assignment: Assignment<bool> = Assignment::from(HashMap::from([
+            ("A".to_string(), false),
+            ("B".to_string(), true),
+        ]));
+        assert_eq!(
+            compiled.evaluate_assignment(&assignment).unwrap_err(),
+            ParseError::UnboundVariable {
+                name: "C".to_string(),
+                position: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_sets() {
+        let mut evaluator: ExpressionEvaluator<Vec<i32>, SetOperations> =
+            ExpressionEvaluator::<Vec<i32>, SetOperations>::new();
+        let hash: HashMap<String, Vec<i32>> = HashMap::from([
+            ("A".to_string(), vec![0, 1, 2]),
+            ("B".to_string(), vec![0, 3, 4]),
+        ]);
+        let mut compiled = evaluator.compile_formula("A!B|").unwrap();
+        let mut result = compiled
+            .evaluate_assignment(&Assignment::from(&hash))
+            .unwrap();
+        result.sort();
+        let mut expected = evaluator.evaluate("A!B|", Some(&hash)).unwrap();
+        expected.sort();
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::aux::expresion_eval::ExprNode;
+use crate::aux::Algebra;
+
+/*
+    A connective registered on top of the core ones (! & | ^ > =).
+    evaluate gives its value and rewrite writes it with the core connectives,
+    which is what NNF and CNF work on.
+*/
+pub enum Connective<T> {
+    Unary {
+        evaluate: fn(&T, Option<&Vec<T>>) -> T,
+        rewrite: fn(ExprNode<T>) -> ExprNode<T>,
+    },
+    Binary {
+        evaluate: fn(&T, &T, Option<&Vec<T>>) -> T,
+        rewrite: fn(ExprNode<T>, ExprNode<T>) -> ExprNode<T>,
+    },
+}
+
+// Only function pointers inside, so it can be copied whatever T is
+impl<T> Clone for Connective<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for Connective<T> {}
+
+impl<T> Connective<T> {
+    pub fn arity(&self) -> usize {
+        match self {
+            Connective::Unary { .. } => 1,
+            Connective::Binary { .. } => 2,
+        }
+    }
+}
+
+/*
+    NAND (A ↑ B ≡ ¬(A ∧ B))
+    A	B	A ↑ B
+    0	0	1
+    0	1	1
+    1	0	1
+    1	1	0
+*/
+fn nand<T, O: Algebra<T>>(a: &T, b: &T, universal: Option<&Vec<T>>) -> T {
+    O::negation(&O::conjunction(a, b, universal), universal)
+}
+
+fn nand_rewrite<T>(a: ExprNode<T>, b: ExprNode<T>) -> ExprNode<T> {
+    ExprNode::UnaryOp(
+        '!',
+        Box::new(ExprNode::BinaryOp('&', Box::new(a), Box::new(b))),
+    )
+}
+
+// NOR (A ↓ B ≡ ¬(A ∨ B)), true only when both are false
+fn nor<T, O: Algebra<T>>(a: &T, b: &T, universal: Option<&Vec<T>>) -> T {
+    O::negation(&O::disjunction(a, b, universal), universal)
+}
+
+fn nor_rewrite<T>(a: ExprNode<T>, b: ExprNode<T>) -> ExprNode<T> {
+    ExprNode::UnaryOp(
+        '!',
+        Box::new(ExprNode::BinaryOp('|', Box::new(a), Box::new(b))),
+    )
+}
+
+// XNOR (A ⊙ B ≡ ¬(A ⊕ B)), the same as equivalence
+fn xnor<T, O: Algebra<T>>(a: &T, b: &T, universal: Option<&Vec<T>>) -> T {
+    O::logical_equivalence(a, b, universal)
+}
+
+fn xnor_rewrite<T>(a: ExprNode<T>, b: ExprNode<T>) -> ExprNode<T> {
+    ExprNode::BinaryOp('=', Box::new(a), Box::new(b))
+}
+
+// Converse implication (A ← B ≡ B → A)
+fn converse_implication<T, O: Algebra<T>>(a: &T, b: &T, universal: Option<&Vec<T>>) -> T {
+    O::implication(b, a, universal)
+}
+
+fn converse_implication_rewrite<T>(a: ExprNode<T>, b: ExprNode<T>) -> ExprNode<T> {
+    ExprNode::BinaryOp('>', Box::new(b), Box::new(a))
+}
+
+// NAND ↑, NOR ↓, XNOR ⊙ and converse implication ←, defined with the algebra O
+pub fn standard_connectives<T, O: Algebra<T>>() -> Vec<(char, Connective<T>)> {
+    vec![
+        (
+            '↑',
+            Connective::Binary {
+                evaluate: nand::<T, O>,
+                rewrite: nand_rewrite,
+            },
+        ),
+        (
+            '↓',
+            Connective::Binary {
+                evaluate: nor::<T, O>,
+                rewrite: nor_rewrite,
+            },
+        ),
+        (
+            '⊙',
+            Connective::Binary {
+                evaluate: xnor::<T, O>,
+                rewrite: xnor_rewrite,
+            },
+        ),
+        (
+            '←',
+            Connective::Binary {
+                evaluate: converse_implication::<T, O>,
+                rewrite: converse_implication_rewrite,
+            },
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
+    use crate::aux::Connective;
+    use crate::boolean_operations::BooleanOperations;
+    use crate::sets::eval_set::eval_set::SetOperations;
+    use crate::truth_table::generate_truth_table;
+    use std::collections::HashMap;
+
+    fn evaluator() -> ExpressionEvaluator<bool, BooleanOperations> {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        evaluator
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut evaluator = evaluator();
+        let expected = [
+            ("↑", [true, true, true, false]),
+            ("↓", [true, false, false, false]),
+            ("⊙", [true, false, false, true]),
+            ("←", [true, false, true, true]),
+        ];
+        for (symbol, values) in expected {
+            for (i, operands) in ["00", "01", "10", "11"].iter().enumerate() {
+                let formula = format!("{}{}", operands, symbol);
+                assert_eq!(
+                    evaluator.evaluate(&formula, None).unwrap(),
+                    values[i],
+                    "{}",
+                    formula
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_truth_table_nnf_cnf() {
+        let mut evaluator = evaluator();
+        let pairs = [
+            ("AB↑", "AB&!"),
+            ("AB↓C↑", "AB|!C&!"),
+            ("AB⊙C←", "CAB=>"),
+            ("AB↑!", "AB&"),
+        ];
+        for (custom, core) in pairs {
+            let expected = generate_truth_table(core, &mut evaluator).unwrap();
+            assert_eq!(
+                generate_truth_table(custom, &mut evaluator).unwrap(),
+                expected
+            );
+
+            let nnf = evaluator.negation_normal_form(custom);
+            assert!(!nnf.contains(|c| "↑↓⊙←".contains(c)), "{}", nnf);
+            assert_eq!(
+                generate_truth_table(&nnf, &mut evaluator).unwrap(),
+                expected
+            );
+
+            for truth_table in [true, false] {
+                let cnf = evaluator
+                    .conjunctive_normal_form(custom, Some(truth_table))
+                    .unwrap();
+                assert_eq!(
+                    generate_truth_table(&cnf, &mut evaluator).unwrap(),
+                    expected
+                );
+            }
+        }
+        assert_eq!(evaluator.negation_normal_form("AB↑"), "A!B!|");
+        assert_eq!(evaluator.negation_normal_form("AB↓"), "A!B!&");
+    }
+
+    #[test]
+    fn test_infix() {
+        let evaluator = evaluator();
+        assert_eq!(evaluator.infix_to_rpn("A ↑ B ↑ C").unwrap(), "AB↑C↑");
+        assert_eq!(evaluator.infix_to_rpn("A | B ↓ C & D").unwrap(), "ABCD&↓|");
+        let tree = evaluator.build_tree("AB↑C←", true, None).unwrap();
+        let printed = evaluator.print_formula(&tree);
+        assert_eq!(printed, "(A ↑ B) ← C");
+        assert_eq!(evaluator.infix_to_rpn(&printed).unwrap(), "AB↑C←");
+    }
+
+    #[test]
+    fn test_register() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // A user defined "at least one is false" written as #
+        fn not_both(a: &bool, b: &bool, _universal: Option<&Vec<bool>>) -> bool {
+            !(*a && *b)
+        }
+        fn not_both_rewrite(a: ExprNode<bool>, b: ExprNode<bool>) -> ExprNode<bool> {
+            ExprNode::BinaryOp(
+                '|',
+                Box::new(ExprNode::UnaryOp('!', Box::new(a))),
+                Box::new(ExprNode::UnaryOp('!', Box::new(b))),
+            )
+        }
+        let connective = Connective::Binary {
+            evaluate: not_both,
+            rewrite: not_both_rewrite,
+        };
+        assert_eq!(connective.arity(), 2);
+        assert!(evaluator.evaluate("11#", None).is_err());
+        evaluator.register_connective('#', connective).unwrap();
+        assert!(!evaluator.evaluate("11#", None).unwrap());
+        assert_eq!(evaluator.negation_normal_form("AB#!"), "AB&");
+
+        // Letters, core operators and their aliases can't be taken
+        assert!(evaluator.register_connective('#', connective).is_err());
+        assert!(evaluator.register_connective('&', connective).is_err());
+        assert!(evaluator.register_connective('¬', connective).is_err());
+        assert!(evaluator.register_connective('x', connective).is_err());
+        assert!(evaluator.register_connective('(', connective).is_err());
+    }
+
+    #[test]
+    fn test_sets() {
+        let mut evaluator: ExpressionEvaluator<Vec<i32>, SetOperations> =
+            ExpressionEvaluator::<Vec<i32>, SetOperations>::new();
+        evaluator.register_standard_connectives();
+        let hash: HashMap<String, Vec<i32>> = HashMap::from([
+            ("A".to_string(), vec![0, 1, 2]),
+            ("B".to_string(), vec![0, 3, 4]),
+        ]);
+        let mut nand = evaluator.evaluate("AB↑", Some(&hash)).unwrap();
+        nand.sort();
+        assert_eq!(nand, vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::aux::expr_arena::{DagNode, ExprArena, NodeId};
+use crate::aux::expresion_eval::ExprNode;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// How many subtree results are kept unless a different limit is set
+pub const DEFAULT_CACHE_LIMIT: usize = 4096;
+
+/*
+    Results of subtrees already evaluated. Subtrees are interned in an arena and results
+    are keyed by their id, so two subtrees share an entry only when they are equal,
+    a hash collision can't hand out the result of another one.
+    A result is only valid in the context it was computed in: the values of the sets algebra
+    depend on the universe, so entering a different context drops every entry.
+    Once limit entries are stored new results are not kept, and the arena is dropped
+    with them when it holds more than limit nodes.
+*/
+#[derive(Debug, Clone)]
+pub struct EvaluationCache<T> {
+    enabled: bool,
+    limit: usize,
+    // The variables and their values, sorted by name
+    context: Option<Vec<(String, T)>>,
+    arena: ExprArena<T>,
+    entries: HashMap<NodeId, T>,
+}
+
+impl<T> EvaluationCache<T> {
+    pub fn new(enabled: bool, limit: usize) -> Self {
+        EvaluationCache {
+            enabled,
+            limit,
+            context: None,
+            arena: ExprArena::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        if self.entries.len() > limit {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.arena = ExprArena::new();
+        self.context = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T: Clone + Eq + Hash> EvaluationCache<T> {
+    // Starts evaluating in context, keeping the entries only if it is the same as the last one
+    pub fn enter(&mut self, context: Vec<(String, T)>) {
+        if self.context.as_ref() != Some(&context) || !self.enabled || self.arena.len() > self.limit
+        {
+            self.clear();
+            self.context = Some(context);
+        }
+    }
+
+    // Id of the subtree in the arena of the cache, the same for every equal subtree
+    pub fn intern(&mut self, node: &ExprNode<T>) -> NodeId {
+        self.arena.insert_expr(node)
+    }
+
+    pub fn node(&self, id: NodeId) -> &DagNode<T> {
+        self.arena.node(id)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.entries.get(&id)
+    }
+
+    pub fn insert(&mut self, id: NodeId, value: &T) {
+        if self.enabled && self.entries.len() < self.limit {
+            self.entries.insert(id, value.clone());
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use crate::aux::expresion_eval::ExprNode;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+// Index of a node in an ExprArena, only meaningful for the arena that returned it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+// Same shapes as ExprNode, with children referred to by id instead of boxed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DagNode<T> {
+    Const(T),
+    Var(String),
+    UnaryOp(char, NodeId),
+    BinaryOp(char, NodeId, NodeId),
+}
+
+/*
+    Stores formulas as a DAG where every distinct subformula exists once.
+    Adding a node that is already in the arena returns the id it has, so two ids are equal
+    exactly when the subformulas are structurally equal, and a subformula used many times
+    costs a single node. Children are always added before their parents,
+    so ids are in topological order.
+*/
+#[derive(Debug, Clone)]
+pub struct ExprArena<T> {
+    nodes: Vec<DagNode<T>>,
+    ids: HashMap<DagNode<T>, NodeId>,
+}
+
+impl<T> ExprArena<T> {
+    pub fn new() -> Self {
+        ExprArena {
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> ExprArena<T> {
+    // Returns the id of node, adding it if no equal node is stored yet
+    pub fn intern(&mut self, node: DagNode<T>) -> NodeId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let id: NodeId = NodeId(self.nodes.len());
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+        id
+    }
+
+    pub fn constant(&mut self, value: T) -> NodeId {
+        self.intern(DagNode::Const(value))
+    }
+
+    pub fn var(&mut self, name: &str) -> NodeId {
+        self.intern(DagNode::Var(name.to_string()))
+    }
+
+    pub fn unary(&mut self, op: char, child: NodeId) -> NodeId {
+        self.intern(DagNode::UnaryOp(op, child))
+    }
+
+    pub fn binary(&mut self, op: char, left: NodeId, right: NodeId) -> NodeId {
+        self.intern(DagNode::BinaryOp(op, left, right))
+    }
+
+    pub fn node(&self, id: NodeId) -> &DagNode<T> {
+        &self.nodes[id.0]
+    }
+
+    // Number of nodes stored for every formula added so far
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Number of distinct nodes of the formula rooted at root, shared subformulas counted once
+    pub fn node_count(&self, root: NodeId) -> usize {
+        let mut seen: HashSet<NodeId> = HashSet::new();
+        let mut pending: Vec<NodeId> = vec![root];
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            match self.node(id) {
+                DagNode::Const(_) | DagNode::Var(_) => {}
+                DagNode::UnaryOp(_, child) => pending.push(*child),
+                DagNode::BinaryOp(_, left, right) => {
+                    pending.push(*left);
+                    pending.push(*right);
+                }
+            }
+        }
+        seen.len()
+    }
+
+    pub fn insert_expr(&mut self, node: &ExprNode<T>) -> NodeId {
+        self.insert_expr_bound(node, &[])
+    }
+
+    /*
+        Like insert_expr, but the variables named #0, #1, ... stand for the nodes in bound.
+        The lexer never reads # in a name, so they can't clash with a real variable.
+        Used to apply the rewrites of registered connectives to children already in the arena.
+    */
+    pub(crate) fn insert_expr_bound(&mut self, node: &ExprNode<T>, bound: &[NodeId]) -> NodeId {
+        match node {
+            ExprNode::Const(value) => self.constant(value.clone()),
+            ExprNode::Var(name) => match name
+                .strip_prefix('#')
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| bound.get(index))
+            {
+                Some(&id) => id,
+                None => self.var(name),
+            },
+            ExprNode::UnaryOp(op, child) => {
+                let child: NodeId = self.insert_expr_bound(child, bound);
+                self.unary(*op, child)
+            }
+            ExprNode::BinaryOp(op, left, right) => {
+                let left: NodeId = self.insert_expr_bound(left, bound);
+                let right: NodeId = self.insert_expr_bound(right, bound);
+                self.binary(*op, left, right)
+            }
+        }
+    }
+
+    // Expands the formula back into a tree, shared subformulas are copied at every use
+    pub fn to_expr(&self, root: NodeId) -> ExprNode<T> {
+        match self.node(root) {
+            DagNode::Const(value) => ExprNode::Const(value.clone()),
+            DagNode::Var(name) => ExprNode::Var(name.clone()),
+            DagNode::UnaryOp(op, child) => ExprNode::UnaryOp(*op, Box::new(self.to_expr(*child))),
+            DagNode::BinaryOp(op, left, right) => ExprNode::BinaryOp(
+                *op,
+                Box::new(self.to_expr(*left)),
+                Box::new(self.to_expr(*right)),
+            ),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Default for ExprArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aux::{Assignment, ExpressionEvaluator};
+    use crate::boolean_operations::BooleanOperations;
+    use crate::truth_table::generate_truth_table;
+
+    fn evaluator() -> ExpressionEvaluator<bool, BooleanOperations> {
+        ExpressionEvaluator::<bool, BooleanOperations>::new()
+    }
+
+    #[test]
+    fn test_hash_consing() {
+        let evaluator = evaluator();
+        let mut arena: ExprArena<bool> = ExprArena::new();
+        // A&B appears twice but is stored once: A, B, A&B and the |
+        let root = arena.insert_expr(&evaluator.parse("AB&AB&|").unwrap());
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.node_count(root), 4);
+
+        let a = arena.var("A");
+        let b = arena.var("B");
+        let and = arena.binary('&', a, b);
+        assert!(
+            matches!(arena.node(root), DagNode::BinaryOp('|', left, right) if *left == and && *right == and)
+        );
+        assert_ne!(arena.binary('&', b, a), and);
+
+        let tree = evaluator.parse("x1 reset_n>0=!").unwrap();
+        let id = arena.insert_expr(&tree);
+        assert_eq!(evaluator.to_rpn(&arena.to_expr(id)), "x1 reset_n>0=!");
+        assert_eq!(arena.insert_expr(&tree), id);
+    }
+
+    #[test]
+    fn test_equivalence_chain() {
+        let mut evaluator = evaluator();
+        // ((x0 = x1) = x2) = ... doubles in size at every level of a tree NNF
+        let names: Vec<String> = (0..24).map(|i| format!("x{}", i)).collect();
+        let formula: String = evaluator.infix_to_rpn(&names.join(" = ")).unwrap();
+        let mut arena: ExprArena<bool> = ExprArena::new();
+        let root = arena.insert_expr(&evaluator.parse(&formula).unwrap());
+        let nnf = evaluator.to_nnf_dag(&mut arena, root);
+        assert!(arena.node_count(nnf) < 10 * names.len());
+
+        let assignment: Assignment<bool> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i % 3 == 0))
+            .collect();
+        assert_eq!(
+            evaluator.evaluate_dag(&arena, nnf, &assignment).unwrap(),
+            evaluator.evaluate_dag(&arena, root, &assignment).unwrap()
+        );
+
+        // Small enough to expand and compare with the tree functions
+        let formula = "AB=C=D^";
+        let root = arena.insert_expr(&evaluator.parse(formula).unwrap());
+        let nnf = evaluator.to_nnf_dag(&mut arena, root);
+        assert_eq!(
+            evaluator.dag_to_rpn(&arena, nnf),
+            evaluator.negation_normal_form(formula)
+        );
+        let cnf = evaluator.to_cnf_dag(&mut arena, root);
+        let rpn = evaluator.dag_to_rpn(&arena, cnf);
+        assert_eq!(
+            generate_truth_table(&rpn, &mut evaluator).unwrap(),
+            generate_truth_table(formula, &mut evaluator).unwrap()
+        );
+        assert_eq!(evaluator.print_dag(&arena, root), "((A ↔ B) ↔ C) ⊕ D");
+    }
+
+    #[test]
+    fn test_connectives() {
+        let mut evaluator = evaluator();
+        evaluator.register_standard_connectives();
+        let mut arena: ExprArena<bool> = ExprArena::new();
+        for formula in ["AB↑C↓!", "AB⊙C←"] {
+            let root = arena.insert_expr(&evaluator.parse(formula).unwrap());
+            let nnf = evaluator.to_nnf_dag(&mut arena, root);
+            assert_eq!(
+                evaluator.dag_to_rpn(&arena, nnf),
+                evaluator.negation_normal_form(formula)
+            );
+        }
+    }
+}
@@ -1,11 +1,21 @@
-use crate::aux::Algebra;
-use regex::Regex;
+use crate::aux::check_only_vars::formula_variables;
+use crate::aux::compiled_formula::{CompiledFormula, Instruction};
+use crate::aux::connectives::{standard_connectives, Connective};
+use crate::aux::evaluation_cache::{EvaluationCache, DEFAULT_CACHE_LIMIT};
+use crate::aux::expr_arena::{DagNode, ExprArena, NodeId};
+use crate::aux::lexer::{
+    is_reserved_symbol, join_rpn, operator_symbol, rpn_operand, tokenize_infix, FormulaStyle,
+    Lexeme, Lexer, Token,
+};
+use crate::aux::{check_only_vars, Algebra, Assignment, ParseError};
 use std::collections::HashMap;
+use std::hash::Hash;
 
 pub struct ExpressionEvaluator<T, O: Algebra<T>> {
     operations: HashMap<char, fn(&T, &T, Option<&Vec<T>>) -> T>,
     unary_operations: HashMap<char, fn(&T, Option<&Vec<T>>) -> T>,
-    cache: HashMap<String, T>,
+    connectives: HashMap<char, Connective<T>>,
+    cache: EvaluationCache<T>,
     _marker: std::marker::PhantomData<O>,
 }
 
@@ -39,11 +49,31 @@ pub enum ValueType {
 #[derive(Debug, Clone)]
 pub enum ExprNode<T> {
     Const(T),
-    Var(char),
+    Var(String),
     UnaryOp(char, Box<ExprNode<T>>),
     BinaryOp(char, Box<ExprNode<T>>, Box<ExprNode<T>>),
 }
 
+/*
+    Binding strength of the binary connectives in infix notation, loosest first:
+    = (equivalence), > (implication), | (or), ^ (xor), & (and).
+    Registered binary connectives bind like ^, negation and unary connectives tighter than all of them.
+*/
+fn infix_precedence(op: char) -> u8 {
+    match op {
+        '=' => 1,
+        '>' => 2,
+        '|' => 3,
+        '&' => 5,
+        _ => 4,
+    }
+}
+
+// Implication is right associative: A > B > C is A > (B > C)
+fn is_right_associative(op: char) -> bool {
+    op == '>'
+}
+
 impl<T, O> ExpressionEvaluator<T, O>
 where
     T: From<LogicValue> + Clone + std::fmt::Debug, // T must be Debug
@@ -70,21 +100,100 @@ where
         Self {
             operations,
             unary_operations,
-            cache: HashMap::new(),
+            connectives: HashMap::new(),
+            cache: EvaluationCache::new(true, DEFAULT_CACHE_LIMIT),
             _marker: std::marker::PhantomData,
         }
     }
 
+    /*
+        Adds a connective written with symbol. It is evaluated with its own function,
+        and NNF and CNF replace it with its rewrite into the core connectives.
+        Letters, digits, parentheses and symbols that already mean something can't be used.
+    */
+    pub fn register_connective(
+        &mut self,
+        symbol: char,
+        connective: Connective<T>,
+    ) -> Result<(), String> {
+        if is_reserved_symbol(symbol)
+            || self.operations.contains_key(&symbol)
+            || self.unary_operations.contains_key(&symbol)
+        {
+            return Err(format!("Error: Symbol '{}' is already in use", symbol));
+        }
+        match connective {
+            Connective::Unary { evaluate, .. } => {
+                self.unary_operations.insert(symbol, evaluate);
+            }
+            Connective::Binary { evaluate, .. } => {
+                self.operations.insert(symbol, evaluate);
+            }
+        }
+        self.connectives.insert(symbol, connective);
+        Ok(())
+    }
+
+    // Registers NAND ↑, NOR ↓, XNOR ⊙ and converse implication ←
+    pub fn register_standard_connectives(&mut self) {
+        for (symbol, connective) in standard_connectives::<T, O>() {
+            // Already registered by hand is fine
+            let _ = self.register_connective(symbol, connective);
+        }
+    }
+
+    // Replaces a registered connective at the root of node with its rewrite, until the root is a core one
+    pub(crate) fn rewrite_connective(&self, node: ExprNode<T>) -> ExprNode<T> {
+        match node {
+            ExprNode::UnaryOp(op, child) => match self.connectives.get(&op) {
+                Some(Connective::Unary { rewrite, .. }) => self.rewrite_connective(rewrite(*child)),
+                _ => ExprNode::UnaryOp(op, child),
+            },
+            ExprNode::BinaryOp(op, left, right) => match self.connectives.get(&op) {
+                Some(Connective::Binary { rewrite, .. }) => {
+                    self.rewrite_connective(rewrite(*left, *right))
+                }
+                _ => ExprNode::BinaryOp(op, left, right),
+            },
+            _ => node,
+        }
+    }
+
+    // rewrite_connective for a node of an arena, the rewrite is added to the arena
+    pub(crate) fn rewrite_connective_dag(&self, arena: &mut ExprArena<T>, id: NodeId) -> NodeId
+    where
+        T: Eq + Hash,
+    {
+        // The children are passed to the rewrite as the placeholders #0 and #1
+        let placeholder = |index: usize| ExprNode::Var(format!("#{}", index));
+        let rewritten: NodeId = match arena.node(id).clone() {
+            DagNode::UnaryOp(op, child) => match self.connectives.get(&op) {
+                Some(Connective::Unary { rewrite, .. }) => {
+                    arena.insert_expr_bound(&rewrite(placeholder(0)), &[child])
+                }
+                _ => return id,
+            },
+            DagNode::BinaryOp(op, left, right) => match self.connectives.get(&op) {
+                Some(Connective::Binary { rewrite, .. }) => arena
+                    .insert_expr_bound(&rewrite(placeholder(0), placeholder(1)), &[left, right]),
+                _ => return id,
+            },
+            _ => return id,
+        };
+        self.rewrite_connective_dag(arena, rewritten)
+    }
+
     fn validate_hash(
         &self,
         expression: &str,
         hash: Option<&HashMap<String, T>>,
-    ) -> Result<bool, String> {
+    ) -> Result<bool, ParseError> {
         match hash {
             Some(h) => {
+                let variables: Vec<String> = formula_variables(expression);
                 for key in h.keys() {
-                    if !expression.contains(key) {
-                        return Err(format!("Variable '{}' not found", key));
+                    if !variables.contains(key) {
+                        return Err(ParseError::UnusedVariable { name: key.clone() });
                     }
                 }
                 Ok(true)
@@ -98,96 +207,523 @@ where
         expression: &str,
         var: bool,
         hash: Option<&HashMap<String, T>>,
-    ) -> Result<ExprNode<T>, String> {
+    ) -> Result<ExprNode<T>, ParseError> {
+        self.parse_rpn(expression, Some(var), hash)
+    }
+
+    // Parses an RPN formula once, keeping variables as Var leaves and 0/1 as constants
+    pub fn parse(&self, expression: &str) -> Result<ExprNode<T>, ParseError> {
+        self.parse_rpn(expression, None, None)
+    }
+
+    // var set to Some(true) or Some(false) only accepts variables or only constants, None takes both
+    fn parse_rpn(
+        &self,
+        expression: &str,
+        var: Option<bool>,
+        hash: Option<&HashMap<String, T>>,
+    ) -> Result<ExprNode<T>, ParseError> {
         let use_hash = self.validate_hash(expression, hash)?;
-        let mut stack: Vec<ExprNode<T>> = Vec::new();
-        for c in expression.chars() {
-            if c.is_ascii_uppercase() {
-                if use_hash {
-                    if let Some(hash) = hash {
-                        if let Some(value) = hash.get(&c.to_string()) {
-                            stack.push(ExprNode::Const(value.clone()));
-                        }
+        // Every operand remembers the byte offset where its subexpression starts
+        let mut stack: Vec<(ExprNode<T>, usize)> = Vec::new();
+        for lexeme in Lexer::new(expression) {
+            let Lexeme {
+                position: i,
+                text,
+                token,
+            } = lexeme?;
+            match token {
+                Token::Var(_) | Token::Const(_) => {
+                    let node: ExprNode<T> =
+                        self.operand_node(token, i, text, var, use_hash, hash)?;
+                    stack.push((node, i));
+                }
+                Token::Operator(c) if self.unary_operations.contains_key(&c) => {
+                    if let Some((expr, start)) = stack.pop() {
+                        stack.push((ExprNode::UnaryOp(c, Box::new(expr)), start));
+                    } else {
+                        return Err(ParseError::OperandUnderflow {
+                            operator: c,
+                            position: i,
+                            expected: 1,
+                            found: 0,
+                        });
                     }
-                } else if var {
-                    stack.push(ExprNode::Var(c));
-                } else {
-                    return Err(format!("Error: Invalid character '{}'", c));
                 }
-            } else if !var && (c == '0' || c == '1') {
-                stack.push(ExprNode::Const(
-                    LogicValue {
-                        value: ValueType::Bool(c == '1'),
+                Token::Operator(c) if self.operations.contains_key(&c) => {
+                    if stack.len() < 2 {
+                        return Err(ParseError::OperandUnderflow {
+                            operator: c,
+                            position: i,
+                            expected: 2,
+                            found: stack.len(),
+                        });
                     }
-                    .into(),
-                ));
-            } else if let Some(_) = self.unary_operations.get(&c) {
-                if let Some(expr) = stack.pop() {
-                    stack.push(ExprNode::UnaryOp(c, Box::new(expr)));
-                } else {
-                    return Err(format!(
-                        "Error: Not enough operands for unary operator '{}'",
-                        c
+                    let (right, _) = stack.pop().unwrap();
+                    let (left, start) = stack.pop().unwrap();
+                    stack.push((
+                        ExprNode::BinaryOp(c, Box::new(left), Box::new(right)),
+                        start,
                     ));
                 }
-            } else if let Some(_) = self.operations.get(&c) {
-                if let (Some(right), Some(left)) = (stack.pop(), stack.pop()) {
-                    stack.push(ExprNode::BinaryOp(c, Box::new(left), Box::new(right)));
-                } else {
-                    return Err(format!(
-                        "Error: Not enough operands for binary operator '{}'",
-                        c
-                    ));
+                Token::Operator(_) => {
+                    return Err(ParseError::UnknownCharacter {
+                        position: i,
+                        snippet: text.to_string(),
+                    })
+                }
+                // RPN doesn't need parentheses
+                Token::OpenParen | Token::CloseParen => {
+                    return Err(ParseError::UnexpectedToken {
+                        position: i,
+                        snippet: text.to_string(),
+                    })
                 }
-            } else {
-                return Err(format!("Error: Invalid character '{}'", c));
             }
         }
-        if stack.len() == 1 {
-            Ok(stack.pop().unwrap())
-        } else {
-            Err("Error: Malformed expression".to_string())
+        match stack.len() {
+            0 => Err(ParseError::EmptyInput),
+            1 => Ok(stack.pop().unwrap().0),
+            count => {
+                // The second operand is the first one no operator ever consumed
+                let position: usize = stack[1].1;
+                let end: usize = stack.get(2).map_or(expression.len(), |&(_, next)| next);
+                Err(ParseError::LeftoverOperands {
+                    count,
+                    position,
+                    snippet: expression[position..end].trim_end().to_string(),
+                })
+            }
         }
     }
 
-    fn evaluate_tree(&mut self, node: &ExprNode<T>, universal: Option<&Vec<T>>) -> T {
-        let key = self.generate_cache_key(node);
+    /*
+        Turns a variable or constant of an RPN expression into a leaf.
+        With a hash, variables are replaced by their values. Otherwise var decides
+        whether the expression holds only variables, only constants or both.
+    */
+    fn operand_node(
+        &self,
+        token: Token,
+        position: usize,
+        text: &str,
+        var: Option<bool>,
+        use_hash: bool,
+        hash: Option<&HashMap<String, T>>,
+    ) -> Result<ExprNode<T>, ParseError> {
+        match token {
+            Token::Var(name) if use_hash => match hash.and_then(|hash| hash.get(name)) {
+                Some(value) => Ok(ExprNode::Const(value.clone())),
+                None => Err(ParseError::UnboundVariable {
+                    name: name.to_string(),
+                    position: Some(position),
+                }),
+            },
+            Token::Var(name) if var != Some(false) => Ok(ExprNode::Var(name.to_string())),
+            // A formula of constants only, a variable in it has no value
+            Token::Var(name) => Err(ParseError::UnboundVariable {
+                name: name.to_string(),
+                position: Some(position),
+            }),
+            Token::Const(b) if var != Some(true) => Ok(ExprNode::Const(
+                LogicValue {
+                    value: ValueType::Bool(b),
+                }
+                .into(),
+            )),
+            _ => Err(ParseError::UnknownCharacter {
+                position,
+                snippet: text.to_string(),
+            }),
+        }
+    }
+
+    /*
+        Parses an infix formula such as "(A & B) | !C -> D" into the same tree build_tree produces.
+        Every operator spelling known to the lexer is accepted, so the output of print_formula
+        in any style parses back.
+    */
+    pub fn build_tree_infix(&self, expression: &str) -> Result<ExprNode<T>, ParseError> {
+        let tokens: Vec<Lexeme> = tokenize_infix(expression)?;
+        if tokens.is_empty() {
+            return Err(ParseError::EmptyInput);
+        }
+        let mut position: usize = 0;
+        let tree: ExprNode<T> = self.parse_infix_expression(&tokens, &mut position, 1)?;
+
+        match tokens.get(position) {
+            None => Ok(tree),
+            Some(&Lexeme {
+                position: i,
+                text,
+                token: Token::CloseParen,
+            }) => Err(ParseError::UnbalancedParenthesis {
+                position: i,
+                snippet: text.to_string(),
+            }),
+            Some(&Lexeme {
+                position: i,
+                text,
+                token: Token::Operator(op),
+            }) if !self.unary_operations.contains_key(&op) => Err(ParseError::UnknownCharacter {
+                position: i,
+                snippet: text.to_string(),
+            }),
+            Some(&Lexeme {
+                position: i,
+                text,
+                token: Token::Operator(_),
+            }) => Err(ParseError::UnexpectedToken {
+                position: i,
+                snippet: text.to_string(),
+            }),
+            // Two operands next to each other, e.g. "A B"
+            Some(&Lexeme { position: i, .. }) => Err(ParseError::LeftoverOperands {
+                count: 2,
+                position: i,
+                snippet: expression[i..].trim_end().to_string(),
+            }),
+        }
+    }
+
+    // Converts an infix formula to RPN so it can be fed to every function that expects RPN
+    pub fn infix_to_rpn(&self, expression: &str) -> Result<String, ParseError> {
+        Ok(self.to_rpn(&self.build_tree_infix(expression)?))
+    }
+
+    // Precedence climbing: keeps folding operators that bind at least as tight as min_precedence
+    fn parse_infix_expression(
+        &self,
+        tokens: &[Lexeme],
+        position: &mut usize,
+        min_precedence: u8,
+    ) -> Result<ExprNode<T>, ParseError> {
+        let mut left: ExprNode<T> = self.parse_infix_operand(tokens, position)?;
 
-        // If result is cached, return it
-        if let Some(cached_result) = self.cache.get(&key) {
-            return cached_result.clone();
+        while let Some(&Lexeme {
+            position: i,
+            token: Token::Operator(op),
+            ..
+        }) = tokens.get(*position)
+        {
+            let precedence: u8 = infix_precedence(op);
+            if precedence < min_precedence || !self.operations.contains_key(&op) {
+                break;
+            }
+            *position += 1;
+            let next_precedence: u8 = if is_right_associative(op) {
+                precedence
+            } else {
+                precedence + 1
+            };
+            if *position == tokens.len() {
+                return Err(ParseError::OperandUnderflow {
+                    operator: op,
+                    position: i,
+                    expected: 2,
+                    found: 1,
+                });
+            }
+            let right: ExprNode<T> =
+                self.parse_infix_expression(tokens, position, next_precedence)?;
+            left = ExprNode::BinaryOp(op, Box::new(left), Box::new(right));
         }
-        let result = match node {
-            ExprNode::Const(value) => value.clone(),
-            ExprNode::Var(value) => panic!("Can't solve tree with value {}", value),
+        Ok(left)
+    }
+
+    fn parse_infix_operand(
+        &self,
+        tokens: &[Lexeme],
+        position: &mut usize,
+    ) -> Result<ExprNode<T>, ParseError> {
+        let Lexeme {
+            position: i,
+            text,
+            token,
+        } = match tokens.get(*position) {
+            Some(&lexeme) => lexeme,
+            None => return Err(ParseError::EmptyInput),
+        };
+        *position += 1;
+
+        match token {
+            Token::Var(name) => Ok(ExprNode::Var(name.to_string())),
+            Token::Const(b) => Ok(ExprNode::Const(
+                LogicValue {
+                    value: ValueType::Bool(b),
+                }
+                .into(),
+            )),
+            Token::Operator(op) if self.unary_operations.contains_key(&op) => {
+                if *position == tokens.len() {
+                    return Err(ParseError::OperandUnderflow {
+                        operator: op,
+                        position: i,
+                        expected: 1,
+                        found: 0,
+                    });
+                }
+                Ok(ExprNode::UnaryOp(
+                    op,
+                    Box::new(self.parse_infix_operand(tokens, position)?),
+                ))
+            }
+            Token::Operator(op) if self.operations.contains_key(&op) => {
+                Err(ParseError::OperandUnderflow {
+                    operator: op,
+                    position: i,
+                    expected: 2,
+                    found: 0,
+                })
+            }
+            Token::Operator(_) => Err(ParseError::UnknownCharacter {
+                position: i,
+                snippet: text.to_string(),
+            }),
+            Token::OpenParen => {
+                let inner: ExprNode<T> = self.parse_infix_expression(tokens, position, 1)?;
+                match tokens.get(*position) {
+                    Some(Lexeme {
+                        token: Token::CloseParen,
+                        ..
+                    }) => {
+                        *position += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(ParseError::UnbalancedParenthesis {
+                        position: i,
+                        snippet: text.to_string(),
+                    }),
+                }
+            }
+            Token::CloseParen => Err(ParseError::UnexpectedToken {
+                position: i,
+                snippet: text.to_string(),
+            }),
+        }
+    }
+
+    // Evaluates the node of the cache arena, reusing the result of every subtree already evaluated
+    fn evaluate_cached(&mut self, id: NodeId, universal: Option<&Vec<T>>) -> Result<T, ParseError>
+    where
+        T: Eq + Hash,
+    {
+        if let Some(cached_result) = self.cache.get(id) {
+            return Ok(cached_result.clone());
+        }
+        let result: T = match self.cache.node(id).clone() {
+            DagNode::Const(value) => return Ok(value),
+            DagNode::Var(name) => {
+                return Err(ParseError::UnboundVariable {
+                    name,
+                    position: None,
+                })
+            }
+            DagNode::UnaryOp(op, child) => {
+                let func = *self.unary_operations.get(&op).unwrap();
+                func(&self.evaluate_cached(child, universal)?, universal)
+            }
+            DagNode::BinaryOp(op, left, right) => {
+                let func = *self.operations.get(&op).unwrap();
+                func(
+                    &self.evaluate_cached(left, universal)?,
+                    &self.evaluate_cached(right, universal)?,
+                    universal,
+                )
+            }
+        };
+        self.cache.insert(id, &result);
+        Ok(result)
+    }
+
+    // Turns the cache on or off, it is on by default
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache.set_enabled(enabled);
+        self
+    }
+
+    // Maximum number of subtree results kept by the cache
+    pub fn with_cache_limit(mut self, limit: usize) -> Self {
+        self.cache.set_limit(limit);
+        self
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn cache_len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /*
+        Evaluates a parsed tree with the variable values in assignment, so a formula parsed once
+        can be evaluated for as many assignments as needed. The bound values are the universe
+        of the sets algebra, like the values of the hash given to evaluate.
+    */
+    pub fn evaluate_assignment(
+        &self,
+        node: &ExprNode<T>,
+        assignment: &Assignment<T>,
+    ) -> Result<T, ParseError> {
+        self.evaluate_node(node, assignment, Some(assignment.values()))
+    }
+
+    fn evaluate_node(
+        &self,
+        node: &ExprNode<T>,
+        assignment: &Assignment<T>,
+        universal: Option<&Vec<T>>,
+    ) -> Result<T, ParseError> {
+        match node {
+            ExprNode::Const(value) => Ok(value.clone()),
+            ExprNode::Var(name) => match assignment.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(ParseError::UnboundVariable {
+                    name: name.to_string(),
+                    position: None,
+                }),
+            },
             ExprNode::UnaryOp(op, expr) => {
-                let func = self.unary_operations.get(op).unwrap();
-                func(&self.evaluate_tree(expr, universal), universal)
+                let func = *self.unary_operations.get(op).unwrap();
+                Ok(func(
+                    &self.evaluate_node(expr, assignment, universal)?,
+                    universal,
+                ))
             }
             ExprNode::BinaryOp(op, left, right) => {
-                let func = self.operations.get(op).unwrap();
+                let func = *self.operations.get(op).unwrap();
+                Ok(func(
+                    &self.evaluate_node(left, assignment, universal)?,
+                    &self.evaluate_node(right, assignment, universal)?,
+                    universal,
+                ))
+            }
+        }
+    }
+
+    // evaluate_assignment for a formula in an arena, every shared node is evaluated once
+    pub fn evaluate_dag(
+        &self,
+        arena: &ExprArena<T>,
+        root: NodeId,
+        assignment: &Assignment<T>,
+    ) -> Result<T, ParseError>
+    where
+        T: Eq + Hash,
+    {
+        let mut values: HashMap<NodeId, T> = HashMap::new();
+        self.evaluate_dag_node(arena, root, assignment, &mut values)
+    }
+
+    fn evaluate_dag_node(
+        &self,
+        arena: &ExprArena<T>,
+        id: NodeId,
+        assignment: &Assignment<T>,
+        values: &mut HashMap<NodeId, T>,
+    ) -> Result<T, ParseError>
+    where
+        T: Eq + Hash,
+    {
+        if let Some(value) = values.get(&id) {
+            return Ok(value.clone());
+        }
+        let universal: Option<&Vec<T>> = Some(assignment.values());
+        let result: T = match arena.node(id) {
+            DagNode::Const(value) => value.clone(),
+            DagNode::Var(name) => match assignment.get(name) {
+                Some(value) => value.clone(),
+                None => {
+                    return Err(ParseError::UnboundVariable {
+                        name: name.to_string(),
+                        position: None,
+                    })
+                }
+            },
+            DagNode::UnaryOp(op, child) => {
+                let func = *self.unary_operations.get(op).unwrap();
+                func(
+                    &self.evaluate_dag_node(arena, *child, assignment, values)?,
+                    universal,
+                )
+            }
+            DagNode::BinaryOp(op, left, right) => {
+                let func = *self.operations.get(op).unwrap();
                 func(
-                    &self.evaluate_tree(left, universal),
-                    &self.evaluate_tree(right, universal),
+                    &self.evaluate_dag_node(arena, *left, assignment, values)?,
+                    &self.evaluate_dag_node(arena, *right, assignment, values)?,
                     universal,
                 )
             }
         };
-        self.cache.insert(key, result.clone());
-        result
+        values.insert(id, result.clone());
+        Ok(result)
     }
 
-    fn generate_cache_key(&self, node: &ExprNode<T>) -> String {
+    /*
+        Lowers a tree into a CompiledFormula, a flat list of instructions with the variables
+        numbered by slot, for when the same formula is evaluated many times.
+    */
+    pub fn compile(&self, node: &ExprNode<T>) -> CompiledFormula<T> {
+        let mut variables: Vec<String> = Vec::new();
+        self.collect_variables(node, &mut variables);
+        variables.sort();
+        variables.dedup();
+
+        let mut instructions: Vec<Instruction<T>> = Vec::new();
+        let depth: usize = self.lower(node, &variables, &mut instructions);
+        CompiledFormula::new(instructions, variables, depth)
+    }
+
+    pub fn compile_formula(&self, expression: &str) -> Result<CompiledFormula<T>, ParseError> {
+        Ok(self.compile(&self.parse(expression)?))
+    }
+
+    pub(crate) fn collect_variables(&self, node: &ExprNode<T>, variables: &mut Vec<String>) {
         match node {
-            ExprNode::Const(value) => format!("{:?}", value),
-            ExprNode::Var(value) => panic!("Can't solve tree with value {}", value),
-            ExprNode::UnaryOp(op, expr) => format!("{}{}", op, self.generate_cache_key(expr)),
-            ExprNode::BinaryOp(op, left, right) => format!(
-                "({} {} {})",
-                self.generate_cache_key(left),
-                op,
-                self.generate_cache_key(right)
-            ),
+            ExprNode::Const(_) => {}
+            ExprNode::Var(name) => variables.push(name.clone()),
+            ExprNode::UnaryOp(_, child) => self.collect_variables(child, variables),
+            ExprNode::BinaryOp(_, left, right) => {
+                self.collect_variables(left, variables);
+                self.collect_variables(right, variables);
+            }
+        }
+    }
+
+    // Appends the instructions of node in RPN order and returns the stack depth they need
+    fn lower(
+        &self,
+        node: &ExprNode<T>,
+        variables: &[String],
+        instructions: &mut Vec<Instruction<T>>,
+    ) -> usize {
+        match node {
+            ExprNode::Const(value) => {
+                instructions.push(Instruction::Const(value.clone()));
+                1
+            }
+            ExprNode::Var(name) => {
+                let slot: usize = variables.binary_search(name).unwrap();
+                instructions.push(Instruction::Load(slot));
+                1
+            }
+            ExprNode::UnaryOp(op, child) => {
+                let depth: usize = self.lower(child, variables, instructions);
+                instructions.push(Instruction::Unary(
+                    *op,
+                    *self.unary_operations.get(op).unwrap(),
+                ));
+                depth
+            }
+            ExprNode::BinaryOp(op, left, right) => {
+                let left_depth: usize = self.lower(left, variables, instructions);
+                // The left value waits on the stack while the right side runs
+                let right_depth: usize = self.lower(right, variables, instructions) + 1;
+                instructions.push(Instruction::Binary(*op, *self.operations.get(op).unwrap()));
+                left_depth.max(right_depth)
+            }
         }
     }
 
@@ -195,63 +731,119 @@ where
         &mut self,
         expression: &str,
         variables_value: Option<&HashMap<String, T>>,
-    ) -> Result<T, String> {
-        let binary_re = Regex::new(r"^[A-Z!&|ˆ>=]+$").unwrap().is_match(expression);
-        let tree = self.build_tree(expression, binary_re, variables_value)?;
+    ) -> Result<T, ParseError>
+    where
+        T: Eq + Hash,
+    {
+        let tree = self.build_tree(expression, check_only_vars(expression), variables_value)?;
 
+        // The universe in the order of the names, so neither the result nor the cache depend on the hash order
+        let mut bound: Vec<(String, T)> = variables_value
+            .map(|map| {
+                map.iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        bound.sort_by(|a, b| a.0.cmp(&b.0));
         let universal_values: Option<Vec<T>> =
-            variables_value.map(|map| map.values().cloned().collect());
-        Ok(self.evaluate_tree(&tree, universal_values.as_ref()))
+            variables_value.map(|_| bound.iter().map(|(_, value)| value.clone()).collect());
+        // Cached results only hold for the universe they were computed with
+        self.cache.enter(bound);
+
+        let root: NodeId = self.cache.intern(&tree);
+        self.evaluate_cached(root, universal_values.as_ref())
+            .map_err(|err| match err {
+                // Point at the first occurrence of the variable in the source
+                ParseError::UnboundVariable {
+                    name,
+                    position: None,
+                } => ParseError::UnboundVariable {
+                    position: expression.find(name.as_str()),
+                    name,
+                },
+                err => err,
+            })
     }
 
     pub fn to_rpn(&self, node: &ExprNode<T>) -> String {
+        // A formula that is only a name like CLK keeps the space rpn_operand put after it
+        self.to_rpn_recursive(node).trim_end().to_string()
+    }
+
+    fn to_rpn_recursive(&self, node: &ExprNode<T>) -> String {
         match node {
-            ExprNode::Const(value) => format!("{:?}", value),
-            ExprNode::Var(c) => c.to_string(),
+            // Boolean constants are written as 1/0 so the RPN can be parsed back
+            ExprNode::Const(value) => match format!("{:?}", value).as_str() {
+                "true" => "1".to_string(),
+                "false" => "0".to_string(),
+                other => other.to_string(),
+            },
+            ExprNode::Var(name) => rpn_operand(name),
             ExprNode::UnaryOp(op, child) => {
-                let child_rpn = self.to_rpn(child);
+                let child_rpn = self.to_rpn_recursive(child);
                 format!("{}{}", child_rpn, op)
             }
             ExprNode::BinaryOp(op, left, right) => {
-                let left_rpn = self.to_rpn(left);
-                let right_rpn = self.to_rpn(right);
-                format!("{}{}{}", left_rpn, right_rpn, op)
+                let left_rpn = self.to_rpn_recursive(left);
+                let right_rpn = self.to_rpn_recursive(right);
+                format!("{}{}", join_rpn(&left_rpn, &right_rpn), op)
             }
         }
     }
 
+    pub fn dag_to_rpn(&self, arena: &ExprArena<T>, root: NodeId) -> String
+    where
+        T: Eq + Hash,
+    {
+        self.to_rpn(&arena.to_expr(root))
+    }
+
+    pub fn print_dag(&self, arena: &ExprArena<T>, root: NodeId) -> String
+    where
+        T: Eq + Hash,
+    {
+        self.print_formula(&arena.to_expr(root))
+    }
+
     pub fn print_formula(&self, node: &ExprNode<T>) -> String {
-        self.print_formula_recursive(node, 0)
+        self.print_formula_with(node, FormulaStyle::Unicode)
+    }
+
+    // Prints the formula in infix with the operators of the given style, e.g. ∧, & or and
+    pub fn print_formula_with(&self, node: &ExprNode<T>, style: FormulaStyle) -> String {
+        self.print_formula_recursive(node, 0, style)
     }
 
-    fn print_formula_recursive(&self, node: &ExprNode<T>, precedence: u8) -> String {
+    fn print_formula_recursive(
+        &self,
+        node: &ExprNode<T>,
+        precedence: u8,
+        style: FormulaStyle,
+    ) -> String {
         match node {
             ExprNode::Const(value) => format!("{:?}", value),
-            ExprNode::Var(c) => c.to_string(),
-            ExprNode::UnaryOp(_op, child) => {
-                let child_formula = self.print_formula_recursive(child, 10);
-                format!("¬{}", child_formula)
+            ExprNode::Var(name) => name.clone(),
+            ExprNode::UnaryOp(op, child) => {
+                let child_formula = self.print_formula_recursive(child, 10, style);
+                match style {
+                    // Words need a space between the operator and its operand
+                    FormulaStyle::Words => {
+                        format!("{} {}", operator_symbol(*op, style), child_formula)
+                    }
+                    _ => format!("{}{}", operator_symbol(*op, style), child_formula),
+                }
             }
             ExprNode::BinaryOp(op, left, right) => {
-                let (left_formula, right_formula) = match op {
-                    '&' => (
-                        self.print_formula_recursive(left, 2),
-                        self.print_formula_recursive(right, 2),
-                    ),
-                    '|' => (
-                        self.print_formula_recursive(left, 1),
-                        self.print_formula_recursive(right, 1),
-                    ),
-                    _ => (
-                        self.print_formula_recursive(left, 0),
-                        self.print_formula_recursive(right, 0),
-                    ),
-                };
-                let formula = match op {
-                    '&' => format!("{} ∧ {}", left_formula, right_formula),
-                    '|' => format!("{} ∨ {}", left_formula, right_formula),
-                    _ => format!("{} {} {}", left_formula, op, right_formula),
-                };
+                // Nested binary operations are always parenthesized so the output parses back unambiguously
+                let left_formula = self.print_formula_recursive(left, 1, style);
+                let right_formula = self.print_formula_recursive(right, 1, style);
+                let formula = format!(
+                    "{} {} {}",
+                    left_formula,
+                    operator_symbol(*op, style),
+                    right_formula
+                );
 
                 if precedence > 0 {
                     format!("({})", formula)
@@ -261,4 +853,402 @@ where
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean_operations::BooleanOperations;
+    use crate::sets::eval_set::eval_set::SetOperations;
+    use crate::truth_table::generate_truth_table;
+
+    #[test]
+    fn test_infix_precedence() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        assert_eq!(
+            evaluator.infix_to_rpn("(A & B) | !C -> D").unwrap(),
+            "AB&C!|D>"
+        );
+        assert_eq!(evaluator.infix_to_rpn("A | B & C").unwrap(), "ABC&|");
+        assert_eq!(evaluator.infix_to_rpn("A | B ^ C").unwrap(), "ABC^|");
+        assert_eq!(evaluator.infix_to_rpn("A = B > C").unwrap(), "ABC>=");
+        assert_eq!(evaluator.infix_to_rpn("!A & B").unwrap(), "A!B&");
+        assert_eq!(evaluator.infix_to_rpn("!(A & B)").unwrap(), "AB&!");
+        assert_eq!(evaluator.infix_to_rpn("A <-> 1").unwrap(), "A1=");
+    }
+
+    #[test]
+    fn test_infix_associativity() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // Implication groups to the right, every other operator to the left
+        assert_eq!(evaluator.infix_to_rpn("A -> B -> C").unwrap(), "ABC>>");
+        assert_eq!(evaluator.infix_to_rpn("A & B & C").unwrap(), "AB&C&");
+        assert_eq!(evaluator.infix_to_rpn("A | B | C").unwrap(), "AB|C|");
+        assert_eq!(evaluator.infix_to_rpn("A = B = C").unwrap(), "AB=C=");
+    }
+
+    #[test]
+    fn test_infix_round_trip() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formulas = vec![
+            "AB&C!|D>",
+            "ABC>>",
+            "AB>C>",
+            "AB^C=D!&",
+            "AB&!C!|",
+            "A!B!|B!A|&",
+        ];
+        for formula in formulas {
+            let tree = evaluator.build_tree(formula, true, None).unwrap();
+            let printed = evaluator.print_formula(&tree);
+            assert_eq!(
+                evaluator.infix_to_rpn(&printed).unwrap(),
+                formula,
+                "{}",
+                printed
+            );
+        }
+
+        // Constants are printed as true/false and parsed back
+        let tree = evaluator.build_tree_infix("(A & 1) | !0").unwrap();
+        let printed = evaluator.print_formula(&tree);
+        assert_eq!(printed, "(A ∧ true) ∨ ¬false");
+        assert_eq!(evaluator.infix_to_rpn(&printed).unwrap(), "A1&0!|");
+    }
+
+    #[test]
+    fn test_formula_styles() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let tree = evaluator.build_tree("AB&C!|D>E^F=", true, None).unwrap();
+        assert_eq!(
+            evaluator.print_formula_with(&tree, FormulaStyle::Ascii),
+            "((((A & B) | !C) -> D) ^ E) <-> F"
+        );
+        assert_eq!(
+            evaluator.print_formula_with(&tree, FormulaStyle::Unicode),
+            "((((A ∧ B) ∨ ¬C) → D) ⊕ E) ↔ F"
+        );
+        assert_eq!(
+            evaluator.print_formula_with(&tree, FormulaStyle::Words),
+            "((((A and B) or not C) implies D) xor E) iff F"
+        );
+        for style in [
+            FormulaStyle::Ascii,
+            FormulaStyle::Unicode,
+            FormulaStyle::Words,
+        ] {
+            let printed = evaluator.print_formula_with(&tree, style);
+            assert_eq!(evaluator.infix_to_rpn(&printed).unwrap(), "AB&C!|D>E^F=");
+        }
+
+        // The aliases work in RPN too
+        assert!(evaluator.evaluate("10∨1ˆ", None).is_ok());
+        assert_eq!(
+            evaluator.evaluate("10∨1ˆ", None).unwrap(),
+            evaluator.evaluate("10|1^", None).unwrap()
+        );
+        assert!(!evaluator.evaluate("1 0 and", None).unwrap());
+    }
+
+    #[test]
+    fn test_infix_errors() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        assert_eq!(
+            evaluator.build_tree_infix("").unwrap_err(),
+            ParseError::EmptyInput
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("A &").unwrap_err(),
+            ParseError::OperandUnderflow {
+                operator: '&',
+                position: 2,
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("& A").unwrap_err().position(),
+            Some(0)
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("(A | B").unwrap_err(),
+            ParseError::UnbalancedParenthesis {
+                position: 0,
+                snippet: "(".to_string()
+            }
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("A | B)").unwrap_err().span(),
+            Some((5, 6))
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("A B").unwrap_err(),
+            ParseError::LeftoverOperands {
+                count: 2,
+                position: 2,
+                snippet: "B".to_string()
+            }
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("A @ B").unwrap_err().span(),
+            Some((2, 3))
+        );
+        assert_eq!(
+            evaluator.build_tree_infix("A & 2x").unwrap_err(),
+            ParseError::UnknownCharacter {
+                position: 4,
+                snippet: "2x".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // Every word of an infix formula is one name
+        assert_eq!(
+            evaluator.infix_to_rpn("reset_n & x12").unwrap(),
+            "reset_n x12&"
+        );
+        assert_eq!(evaluator.infix_to_rpn("A & x12 | B").unwrap(), "A x12&B|");
+        assert_eq!(
+            evaluator.infix_to_rpn("(Clk_en > A) = 1").unwrap(),
+            "Clk_en A>1="
+        );
+        let tree = evaluator.build_tree("reset_n x12&A|", true, None).unwrap();
+        assert_eq!(evaluator.print_formula(&tree), "(reset_n ∧ x12) ∨ A");
+        assert_eq!(evaluator.to_rpn(&tree), "reset_n x12&A|");
+        assert_eq!(
+            evaluator
+                .build_tree("reset_n x12& A", true, None)
+                .unwrap_err(),
+            ParseError::LeftoverOperands {
+                count: 2,
+                position: 13,
+                snippet: "A".to_string()
+            }
+        );
+
+        // Uppercase names are followed by a space in RPN, so they aren't read as packed letters
+        assert_eq!(
+            evaluator.infix_to_rpn("CLK & reset_n").unwrap(),
+            "CLK reset_n&"
+        );
+        assert_eq!(evaluator.infix_to_rpn("X12 | a").unwrap(), "X12 a|");
+        assert_eq!(evaluator.infix_to_rpn("A & !CLK").unwrap(), "A CLK !&");
+        assert_eq!(evaluator.infix_to_rpn("X12").unwrap(), "X12");
+        for formula in ["CLK reset_n&", "X12 a|", "A CLK !&", "AB&X12 |"] {
+            let tree = evaluator.build_tree(formula, true, None).unwrap();
+            assert_eq!(evaluator.to_rpn(&tree), formula);
+        }
+        let tree = evaluator.build_tree("AB&X12 |", true, None).unwrap();
+        assert_eq!(evaluator.print_formula(&tree), "(A ∧ B) ∨ X12");
+        assert_eq!(formula_variables("CLK AB&|"), vec!["A", "B", "CLK"]);
+    }
+
+    #[test]
+    fn test_many_variables() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // 40 variables, more than the 26 letters
+        let names: Vec<String> = (0..40).map(|i| format!("x{}", i)).collect();
+        let formula: String = evaluator.infix_to_rpn(&names.join(" & ")).unwrap();
+        assert_eq!(formula_variables(&formula).len(), 40);
+
+        let mut values: HashMap<String, bool> =
+            names.iter().map(|name| (name.clone(), true)).collect();
+        assert!(evaluator.evaluate(&formula, Some(&values)).unwrap());
+        values.insert("x39".to_string(), false);
+        assert!(!evaluator.evaluate(&formula, Some(&values)).unwrap());
+    }
+
+    #[test]
+    fn test_rpn_errors() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        assert_eq!(
+            evaluator.evaluate("", None).unwrap_err(),
+            ParseError::EmptyInput
+        );
+        assert_eq!(
+            evaluator.evaluate("1@", None).unwrap_err(),
+            ParseError::UnknownCharacter {
+                position: 1,
+                snippet: "@".to_string()
+            }
+        );
+        assert_eq!(
+            evaluator.evaluate("10&&", None).unwrap_err(),
+            ParseError::OperandUnderflow {
+                operator: '&',
+                position: 3,
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            evaluator.evaluate("!", None).unwrap_err(),
+            ParseError::OperandUnderflow {
+                operator: '!',
+                position: 0,
+                expected: 1,
+                found: 0
+            }
+        );
+        assert_eq!(
+            evaluator.evaluate("10&01|1", None).unwrap_err(),
+            ParseError::LeftoverOperands {
+                count: 3,
+                position: 3,
+                snippet: "01|".to_string()
+            }
+        );
+        // Letters without values are reported where they first appear
+        assert_eq!(
+            evaluator.evaluate("AB|", None).unwrap_err(),
+            ParseError::UnboundVariable {
+                name: "A".to_string(),
+                position: Some(0)
+            }
+        );
+        let hash: HashMap<String, bool> = HashMap::from([("A".to_string(), true)]);
+        assert_eq!(
+            evaluator.evaluate("AB|", Some(&hash)).unwrap_err(),
+            ParseError::UnboundVariable {
+                name: "B".to_string(),
+                position: Some(1)
+            }
+        );
+        // Same for a variable in a formula read as constants only
+        assert_eq!(
+            evaluator.evaluate("1 a&", None).unwrap_err(),
+            ParseError::UnboundVariable {
+                name: "a".to_string(),
+                position: Some(2)
+            }
+        );
+        // The snippet of an error on a copy of a formula, read back from the original
+        assert_eq!(
+            evaluator
+                .evaluate("10&01|1", None)
+                .unwrap_err()
+                .with_source("AB&BA|A"),
+            ParseError::LeftoverOperands {
+                count: 3,
+                position: 3,
+                snippet: "BA|".to_string()
+            }
+        );
+        let hash: HashMap<String, bool> = HashMap::from([("C".to_string(), true)]);
+        assert_eq!(
+            evaluator.evaluate("1!", Some(&hash)).unwrap_err(),
+            ParseError::UnusedVariable {
+                name: "C".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // Both sides of the | are the same subtree and share one entry
+        assert!(evaluator.evaluate("10&!10&!|", None).unwrap());
+        assert_eq!(evaluator.cache_len(), 3);
+        evaluator.clear_cache();
+        assert_eq!(evaluator.cache_len(), 0);
+
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new().with_cache(false);
+        assert!(!evaluator.evaluate("11&0|!", None).unwrap());
+        assert_eq!(evaluator.cache_len(), 0);
+
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new().with_cache_limit(2);
+        assert!(evaluator.evaluate("11&0|1&", None).unwrap());
+        assert_eq!(evaluator.cache_len(), 2);
+    }
+
+    #[test]
+    fn test_cache_universe() {
+        let mut evaluator: ExpressionEvaluator<Vec<i32>, SetOperations> =
+            ExpressionEvaluator::<Vec<i32>, SetOperations>::new();
+        // The complement of A depends on every set of the hash, not only on A
+        let hash: HashMap<String, Vec<i32>> =
+            HashMap::from([("A".to_string(), vec![0]), ("B".to_string(), vec![1])]);
+        assert_eq!(evaluator.evaluate("A!B|", Some(&hash)).unwrap(), vec![1]);
+        let hash: HashMap<String, Vec<i32>> =
+            HashMap::from([("A".to_string(), vec![0]), ("B".to_string(), vec![2])]);
+        assert_eq!(evaluator.evaluate("A!B|", Some(&hash)).unwrap(), vec![2]);
+
+        // The universe is taken in the order of the names, whatever the order of the hash
+        let names: [&str; 4] = ["D", "B", "A", "C"];
+        let hash: HashMap<String, Vec<i32>> = names
+            .iter()
+            .zip([vec![3], vec![1], vec![0], vec![2]])
+            .map(|(name, set)| (name.to_string(), set))
+            .collect();
+        let mut entries: Vec<(String, Vec<i32>)> = hash.clone().into_iter().collect();
+        entries.reverse();
+        let reversed: HashMap<String, Vec<i32>> = entries.into_iter().collect();
+        assert_eq!(
+            evaluator.evaluate("A!BD&&C|", Some(&hash)).unwrap(),
+            vec![2]
+        );
+        assert_eq!(evaluator.evaluate("A!D|BC&&", Some(&hash)).unwrap(), vec![]);
+        assert_eq!(
+            evaluator.evaluate("D!A!&B|C|", Some(&hash)).unwrap(),
+            vec![1, 2]
+        );
+        let cached: usize = evaluator.cache_len();
+        // An equal universe keeps the cache
+        assert_eq!(
+            evaluator.evaluate("D!A!&B|C|", Some(&reversed)).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(evaluator.cache_len(), cached);
+    }
+
+    #[test]
+    fn test_evaluate_assignment() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // Variables and constants can be mixed, the tree is evaluated without reparsing
+        let tree = evaluator.parse("A reset_n|1&").unwrap();
+        let mut assignment: Assignment<bool> = Assignment::new();
+        assignment.set("A", false);
+        assignment.set("reset_n", false);
+        assert!(!evaluator.evaluate_assignment(&tree, &assignment).unwrap());
+        assignment.set("reset_n", true);
+        assert!(evaluator.evaluate_assignment(&tree, &assignment).unwrap());
+
+        let partial: Assignment<bool> = Assignment::from(HashMap::from([("A".to_string(), true)]));
+        assert_eq!(
+            evaluator.evaluate_assignment(&tree, &partial).unwrap_err(),
+            ParseError::UnboundVariable {
+                name: "reset_n".to_string(),
+                position: None
+            }
+        );
+        assert!(evaluator.parse("A B").is_err());
+    }
+
+    #[test]
+    fn test_infix_truth_table() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let rpn = evaluator.infix_to_rpn("(A & B) | !C -> D").unwrap();
+        assert_eq!(
+            generate_truth_table(&rpn, &mut evaluator).unwrap(),
+            generate_truth_table("AB&C!|D>", &mut evaluator).unwrap()
+        );
+        let rpn = evaluator.infix_to_rpn("1 & !0 -> 0").unwrap();
+        assert!(!evaluator.evaluate(&rpn, None).unwrap());
+    }
+}
@@ -0,0 +1,410 @@
+use crate::aux::ParseError;
+use std::collections::VecDeque;
+
+/*
+    Lexer shared by the RPN and infix parsers.
+    Every spelling of an operator is mapped to the single character the evaluator uses:
+    ! negation, & conjunction, | disjunction, ^ exclusive disjunction, > implication, = equivalence.
+    Unknown symbols are passed through as operators so the parser can look them up.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    Var(&'a str),
+    Const(bool),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+}
+
+// A token with its byte offset and source text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lexeme<'a> {
+    pub position: usize,
+    pub text: &'a str,
+    pub token: Token<'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormulaStyle {
+    Ascii,
+    Unicode,
+    Words,
+}
+
+// Operator, then how it's written in the Ascii, Unicode and Words styles
+const OPERATOR_STYLES: [(char, &str, &str, &str); 6] = [
+    ('!', "!", "¬", "not"),
+    ('&', "&", "∧", "and"),
+    ('|', "|", "∨", "or"),
+    ('^', "^", "⊕", "xor"),
+    ('>', "->", "→", "implies"),
+    ('=', "<->", "↔", "iff"),
+];
+
+// Symbols accepted for each operator, multi-character ones first. ˆ (U+02C6) is the caret look-alike
+const SYMBOL_ALIASES: [(&str, char); 17] = [
+    ("<->", '='),
+    ("->", '>'),
+    ("!", '!'),
+    ("¬", '!'),
+    ("&", '&'),
+    ("∧", '&'),
+    ("|", '|'),
+    ("∨", '|'),
+    ("^", '^'),
+    ("ˆ", '^'),
+    ("⊕", '^'),
+    (">", '>'),
+    ("→", '>'),
+    ("=", '='),
+    ("↔", '='),
+    ("(", '('),
+    (")", ')'),
+];
+
+// Reserved words, they can't be used as variable names
+const WORD_ALIASES: [(&str, char); 6] = [
+    ("not", '!'),
+    ("and", '&'),
+    ("or", '|'),
+    ("xor", '^'),
+    ("implies", '>'),
+    ("iff", '='),
+];
+
+// How an operator is written in a given style, unknown operators are written as they are
+pub fn operator_symbol(op: char, style: FormulaStyle) -> String {
+    match OPERATOR_STYLES.iter().find(|(c, _, _, _)| *c == op) {
+        Some((_, ascii, unicode, words)) => match style {
+            FormulaStyle::Ascii => ascii.to_string(),
+            FormulaStyle::Unicode => unicode.to_string(),
+            FormulaStyle::Words => words.to_string(),
+        },
+        None => op.to_string(),
+    }
+}
+
+// Characters that can't name a new operator: word characters, spaces and the symbols above
+pub fn is_reserved_symbol(c: char) -> bool {
+    is_word_char(c)
+        || c.is_whitespace()
+        || SYMBOL_ALIASES
+            .iter()
+            .any(|(alias, _)| alias.chars().eq(std::iter::once(c)))
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// A lowercase letter or '_' makes a word one identifier wherever it is written
+fn is_identifier(word: &str) -> bool {
+    word.chars().any(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+// Names such as CLK or X12 that the packed RPN form would read letter by letter
+fn is_packable_name(name: &str) -> bool {
+    name.len() > 1 && !is_identifier(name)
+}
+
+// Returns the byte offset where the run of word characters starting at start ends
+fn word_end(expression: &str, start: usize) -> usize {
+    expression[start..]
+        .find(|c: char| !is_word_char(c))
+        .map_or(expression.len(), |len| start + len)
+}
+
+/*
+    Splits a run of letters, digits and '_' into lexemes.
+    When packed is set, a run of uppercase letters and digits is the packed form used by RPN:
+    "AB1" is A, B and the constant 1. Any other run is a single identifier such as reset_n,
+    x12 or CLK, unless it is one of the reserved words. true and false are the constants
+    written by print_formula.
+*/
+fn split_word(word: &str, offset: usize, packed: bool) -> Result<Vec<Lexeme<'_>>, ParseError> {
+    let lexeme = |token: Token<'static>| Lexeme {
+        position: offset,
+        text: word,
+        token,
+    };
+    if let Some((_, op)) = WORD_ALIASES.iter().find(|(alias, _)| *alias == word) {
+        return Ok(vec![lexeme(Token::Operator(*op))]);
+    }
+    match word {
+        "true" => Ok(vec![lexeme(Token::Const(true))]),
+        "false" => Ok(vec![lexeme(Token::Const(false))]),
+        _ if is_identifier(word) || (!packed && word.len() > 1) => {
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                return Err(ParseError::UnknownCharacter {
+                    position: offset,
+                    snippet: word.to_string(),
+                });
+            }
+            Ok(vec![Lexeme {
+                position: offset,
+                text: word,
+                token: Token::Var(word),
+            }])
+        }
+        _ => word
+            .char_indices()
+            .map(|(i, c)| {
+                let text: &str = &word[i..i + 1];
+                let token: Token = match c {
+                    'A'..='Z' => Token::Var(text),
+                    '0' | '1' => Token::Const(c == '1'),
+                    _ => {
+                        return Err(ParseError::UnknownCharacter {
+                            position: offset + i,
+                            snippet: c.to_string(),
+                        })
+                    }
+                };
+                Ok(Lexeme {
+                    position: offset + i,
+                    text,
+                    token,
+                })
+            })
+            .collect(),
+    }
+}
+
+pub struct Lexer<'a> {
+    expression: &'a str,
+    position: usize,
+    // RPN reads a word of uppercase letters and digits letter by letter when an operator follows it
+    packed: bool,
+    // Lexemes of a packed word that haven't been returned yet
+    pending: VecDeque<Lexeme<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    // Lexer for RPN, where "AB&" is A and B but "CLK reset_n&" is CLK and reset_n
+    pub fn new(expression: &'a str) -> Self {
+        Lexer {
+            expression,
+            position: 0,
+            packed: true,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // Lexer for infix, where every word is a single name
+    pub fn infix(expression: &'a str) -> Self {
+        Lexer {
+            packed: false,
+            ..Lexer::new(expression)
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Lexeme<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(lexeme) = self.pending.pop_front() {
+            return Some(Ok(lexeme));
+        }
+        let rest: &'a str = &self.expression[self.position..];
+        let start: usize = self.position + (rest.len() - rest.trim_start().len());
+        let c: char = self.expression[start..].chars().next()?;
+
+        if is_word_char(c) {
+            let end: usize = word_end(self.expression, start);
+            self.position = end;
+            // A word followed by a space or the end of the formula is written out in full
+            let packed: bool = self.packed
+                && self.expression[end..]
+                    .chars()
+                    .next()
+                    .is_some_and(|next| !next.is_whitespace());
+            return match split_word(&self.expression[start..end], start, packed) {
+                Ok(lexemes) => {
+                    self.pending.extend(lexemes);
+                    self.pending.pop_front().map(Ok)
+                }
+                Err(err) => {
+                    // Stop after the first error
+                    self.position = self.expression.len();
+                    Some(Err(err))
+                }
+            };
+        }
+
+        let (text, token) = match SYMBOL_ALIASES
+            .iter()
+            .find(|(alias, _)| self.expression[start..].starts_with(alias))
+        {
+            Some((alias, '(')) => (*alias, Token::OpenParen),
+            Some((alias, ')')) => (*alias, Token::CloseParen),
+            Some((alias, op)) => (*alias, Token::Operator(*op)),
+            None => (
+                &self.expression[start..start + c.len_utf8()],
+                Token::Operator(c),
+            ),
+        };
+        self.position = start + text.len();
+        Some(Ok(Lexeme {
+            position: start,
+            text: &self.expression[start..self.position],
+            token,
+        }))
+    }
+}
+
+pub fn tokenize(expression: &str) -> Result<Vec<Lexeme<'_>>, ParseError> {
+    Lexer::new(expression).collect()
+}
+
+pub fn tokenize_infix(expression: &str) -> Result<Vec<Lexeme<'_>>, ParseError> {
+    Lexer::infix(expression).collect()
+}
+
+/*
+    A variable as an RPN operand. Names like CLK are followed by a space,
+    so the operator written after them doesn't make them read as C, L and K.
+*/
+pub fn rpn_operand(name: &str) -> String {
+    if is_packable_name(name) {
+        format!("{} ", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/*
+    Concatenates two pieces of RPN.
+    Single letters and constants can be packed together, but a space is needed
+    when one of the operands meeting at the boundary is a multi-character identifier.
+*/
+pub fn join_rpn(left: &str, right: &str) -> String {
+    let trailing: &str = left.rsplit(|c: char| !is_word_char(c)).next().unwrap_or("");
+    let leading: &str = right.split(|c: char| !is_word_char(c)).next().unwrap_or("");
+    // A name like CLK at the start of right is written by rpn_operand, with a space after it
+    let spaced: bool = is_packable_name(leading)
+        && right[leading.len()..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace);
+    if !trailing.is_empty()
+        && !leading.is_empty()
+        && (is_identifier(trailing) || is_identifier(leading) || spaced)
+    {
+        format!("{} {}", left, right)
+    } else {
+        format!("{}{}", left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(expression: &str) -> Vec<Token<'_>> {
+        tokenize(expression)
+            .unwrap()
+            .into_iter()
+            .map(|lexeme| lexeme.token)
+            .collect()
+    }
+
+    #[test]
+    fn test_aliases() {
+        let expected = vec![
+            Token::Var("A"),
+            Token::Operator('&'),
+            Token::Var("B"),
+            Token::Operator('|'),
+            Token::Operator('!'),
+            Token::Var("C"),
+            Token::Operator('^'),
+            Token::Var("D"),
+            Token::Operator('>'),
+            Token::Var("E"),
+            Token::Operator('='),
+            Token::Var("F"),
+        ];
+        assert_eq!(tokens("A & B | !C ^ D -> E <-> F"), expected);
+        assert_eq!(tokens("A ∧ B ∨ ¬C ⊕ D → E ↔ F"), expected);
+        assert_eq!(tokens("A and B or not C xor D implies E iff F"), expected);
+        assert_eq!(tokens("A & B | !C ˆ D > E = F"), expected);
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            tokens("AB1&"),
+            vec![
+                Token::Var("A"),
+                Token::Var("B"),
+                Token::Const(true),
+                Token::Operator('&')
+            ]
+        );
+        assert_eq!(
+            tokens("reset_n x12 and(true)"),
+            vec![
+                Token::Var("reset_n"),
+                Token::Var("x12"),
+                Token::Operator('&'),
+                Token::OpenParen,
+                Token::Const(true),
+                Token::CloseParen
+            ]
+        );
+        // Unknown symbols are left to the parser
+        assert_eq!(tokens("A@"), vec![Token::Var("A"), Token::Operator('@')]);
+        assert_eq!(
+            tokenize("AB2&"),
+            Err(ParseError::UnknownCharacter {
+                position: 2,
+                snippet: "2".to_string()
+            })
+        );
+        // Uppercase names are packed in RPN only when an operator follows them
+        assert_eq!(
+            tokens("CLK AB&X12 |"),
+            vec![
+                Token::Var("CLK"),
+                Token::Var("A"),
+                Token::Var("B"),
+                Token::Operator('&'),
+                Token::Var("X12"),
+                Token::Operator('|')
+            ]
+        );
+        // and never in infix
+        let infix: Vec<Token> = tokenize_infix("CLK & X12")
+            .unwrap()
+            .into_iter()
+            .map(|lexeme| lexeme.token)
+            .collect();
+        assert_eq!(
+            infix,
+            vec![Token::Var("CLK"), Token::Operator('&'), Token::Var("X12")]
+        );
+        assert_eq!(
+            tokenize_infix("A & 12"),
+            Err(ParseError::UnknownCharacter {
+                position: 4,
+                snippet: "12".to_string()
+            })
+        );
+        let lexemes = tokenize("x1 <-> y").unwrap();
+        assert_eq!((lexemes[1].position, lexemes[1].text), (3, "<->"));
+    }
+
+    #[test]
+    fn test_join_rpn() {
+        assert_eq!(join_rpn("AB&", "C"), "AB&C");
+        assert_eq!(join_rpn("A", "B"), "AB");
+        assert_eq!(join_rpn("x1", "B"), "x1 B");
+        assert_eq!(join_rpn("A", "x1!"), "A x1!");
+        assert_eq!(join_rpn("", "x1"), "x1");
+        assert_eq!(rpn_operand("CLK"), "CLK ");
+        assert_eq!(join_rpn("A", "CLK "), "A CLK ");
+        assert_eq!(join_rpn("CLK ", "B"), "CLK B");
+        assert_eq!(join_rpn("A", "BC&"), "ABC&");
+    }
+}
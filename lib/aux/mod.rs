@@ -2,8 +2,23 @@ pub mod dec_to_bin;
 pub mod traits;
 pub mod expresion_eval;
 pub mod check_only_vars;
+pub mod connectives;
+pub mod lexer;
+pub mod parse_error;
+pub mod assignment;
+pub mod compiled_formula;
+pub mod evaluation_cache;
+pub mod expr_arena;
+pub mod big_uint;
 
-pub use check_only_vars::check_only_vars;
+pub use check_only_vars::{check_only_vars, formula_variables};
 pub use dec_to_bin::to_binary;
 pub use traits::Algebra;
+pub use parse_error::ParseError;
+pub use assignment::Assignment;
+pub use compiled_formula::{CompiledFormula, Instruction};
+pub use expr_arena::{DagNode, ExprArena, NodeId};
+pub use big_uint::BigUint;
+pub use lexer::FormulaStyle;
+pub use connectives::Connective;
 pub use expresion_eval::{ExpressionEvaluator, ExprNode, LogicValue};
\ No newline at end of file
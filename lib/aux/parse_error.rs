@@ -0,0 +1,172 @@
+use std::fmt;
+
+/*
+    Errors raised while parsing or evaluating a formula.
+    Positions are byte offsets into the original expression and the snippet is the offending text,
+    so position..position + snippet.len() is the span to underline.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    EmptyInput,
+    UnknownCharacter {
+        position: usize,
+        snippet: String,
+    },
+    OperandUnderflow {
+        operator: char,
+        position: usize,
+        expected: usize,
+        found: usize,
+    },
+    LeftoverOperands {
+        count: usize,
+        position: usize,
+        snippet: String,
+    },
+    UnexpectedToken {
+        position: usize,
+        snippet: String,
+    },
+    UnbalancedParenthesis {
+        position: usize,
+        snippet: String,
+    },
+    UnboundVariable {
+        name: String,
+        position: Option<usize>,
+    },
+    UnusedVariable {
+        name: String,
+    },
+    // The snippet is the RPN of the part of the formula that is not a clause or a literal
+    NotCnf {
+        snippet: String,
+    },
+}
+
+impl ParseError {
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            ParseError::UnknownCharacter { position, .. }
+            | ParseError::OperandUnderflow { position, .. }
+            | ParseError::LeftoverOperands { position, .. }
+            | ParseError::UnexpectedToken { position, .. }
+            | ParseError::UnbalancedParenthesis { position, .. } => Some(*position),
+            ParseError::UnboundVariable { position, .. } => *position,
+            ParseError::EmptyInput
+            | ParseError::UnusedVariable { .. }
+            | ParseError::NotCnf { .. } => None,
+        }
+    }
+
+    pub fn snippet(&self) -> Option<String> {
+        match self {
+            ParseError::UnknownCharacter { snippet, .. }
+            | ParseError::LeftoverOperands { snippet, .. }
+            | ParseError::UnexpectedToken { snippet, .. }
+            | ParseError::UnbalancedParenthesis { snippet, .. } => Some(snippet.clone()),
+            ParseError::OperandUnderflow { operator, .. } => Some(operator.to_string()),
+            ParseError::NotCnf { snippet } => Some(snippet.clone()),
+            ParseError::UnboundVariable { name, position } => position.map(|_| name.clone()),
+            ParseError::EmptyInput | ParseError::UnusedVariable { .. } => None,
+        }
+    }
+
+    // Byte range of the offending text in the original expression
+    pub fn span(&self) -> Option<(usize, usize)> {
+        match (self.position(), self.snippet()) {
+            (Some(position), Some(snippet)) => Some((position, position + snippet.len())),
+            _ => None,
+        }
+    }
+
+    /*
+        Re-reads the snippet from another source with the same layout,
+        e.g. the original formula when the error came from a copy with variables substituted.
+    */
+    pub fn with_source(self, source: &str) -> ParseError {
+        let text = |position: usize, snippet: String| {
+            source
+                .get(position..position + snippet.len())
+                .map_or(snippet, str::to_string)
+        };
+        match self {
+            ParseError::UnknownCharacter { position, snippet } => ParseError::UnknownCharacter {
+                position,
+                snippet: text(position, snippet),
+            },
+            ParseError::LeftoverOperands {
+                count,
+                position,
+                snippet,
+            } => ParseError::LeftoverOperands {
+                count,
+                position,
+                snippet: text(position, snippet),
+            },
+            ParseError::UnexpectedToken { position, snippet } => ParseError::UnexpectedToken {
+                position,
+                snippet: text(position, snippet),
+            },
+            ParseError::UnbalancedParenthesis { position, snippet } => {
+                ParseError::UnbalancedParenthesis {
+                    position,
+                    snippet: text(position, snippet),
+                }
+            }
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::EmptyInput => write!(f, "Error: Empty expression"),
+            ParseError::UnknownCharacter { position, snippet } => {
+                write!(f, "Error: Invalid character '{}' at position {}", snippet, position)
+            }
+            ParseError::OperandUnderflow {
+                operator,
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Error: Not enough operands for operator '{}' at position {}: expected {}, found {}",
+                operator, position, expected, found
+            ),
+            ParseError::LeftoverOperands {
+                count,
+                position,
+                snippet,
+            } => write!(
+                f,
+                "Error: Malformed expression, {} operands left on the stack, '{}' at position {} is never used",
+                count, snippet, position
+            ),
+            ParseError::UnexpectedToken { position, snippet } => {
+                write!(f, "Error: Unexpected '{}' at position {}", snippet, position)
+            }
+            ParseError::UnbalancedParenthesis { position, snippet } => {
+                write!(f, "Error: Unbalanced '{}' at position {}", snippet, position)
+            }
+            ParseError::UnboundVariable {
+                name,
+                position: Some(position),
+            } => write!(f, "Error: Variable '{}' at position {} has no value", name, position),
+            ParseError::UnboundVariable {
+                name,
+                position: None,
+            } => write!(f, "Error: Variable '{}' has no value", name),
+            ParseError::UnusedVariable { name } => {
+                write!(f, "Error: Variable '{}' not found", name)
+            }
+            ParseError::NotCnf { snippet } => {
+                write!(f, "Error: '{}' is not in conjunctive normal form", snippet)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -6,7 +6,3 @@ pub trait Algebra<T> {
     fn exclusive_disjunction(a: &T, b: &T, universal: Option<&Vec<T>>) -> T;
     fn logical_equivalence(a: &T, b: &T, universal: Option<&Vec<T>>) -> T;
 }
-
-pub trait SatTruthTable {
-    fn sat_truth_table(&mut self, formula: &str) -> bool;
-}
@@ -0,0 +1,495 @@
+use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
+use crate::aux::{Assignment, ParseError};
+use crate::boolean_operations::cnf::cnf::clauses_to_expr;
+use crate::boolean_operations::BooleanOperations;
+use std::fmt;
+use std::ops::Not;
+
+// A variable of a Cnf, by index, or its negation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Literal {
+    var: usize,
+    negated: bool,
+}
+
+impl Literal {
+    pub fn new(var: usize, negated: bool) -> Self {
+        Literal { var, negated }
+    }
+
+    pub fn positive(var: usize) -> Self {
+        Literal::new(var, false)
+    }
+
+    pub fn negative(var: usize) -> Self {
+        Literal::new(var, true)
+    }
+
+    pub fn var(self) -> usize {
+        self.var
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.negated
+    }
+
+    /*
+        2 * var for the variable and 2 * var + 1 for its negation,
+        so solvers can index arrays by literal and code ^ 1 is the opposite literal.
+    */
+    pub fn code(self) -> usize {
+        2 * self.var + self.negated as usize
+    }
+
+    pub fn from_code(code: usize) -> Self {
+        Literal::new(code / 2, code % 2 == 1)
+    }
+
+    // Value of the literal when its variable has the given value
+    pub fn value(self, var_value: bool) -> bool {
+        var_value != self.negated
+    }
+}
+
+impl Not for Literal {
+    type Output = Literal;
+
+    fn not(self) -> Literal {
+        Literal::new(self.var, !self.negated)
+    }
+}
+
+// A disjunction of literals, the empty clause is false
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Clause {
+    literals: Vec<Literal>,
+}
+
+impl Clause {
+    pub fn new(literals: Vec<Literal>) -> Self {
+        Clause { literals }
+    }
+
+    pub fn literals(&self) -> &[Literal] {
+        &self.literals
+    }
+
+    pub fn len(&self) -> usize {
+        self.literals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.literals.is_empty()
+    }
+
+    pub fn is_unit(&self) -> bool {
+        self.literals.len() == 1
+    }
+
+    pub fn contains(&self, literal: Literal) -> bool {
+        self.literals.contains(&literal)
+    }
+
+    pub fn push(&mut self, literal: Literal) {
+        self.literals.push(literal);
+    }
+
+    // A clause with A and !A is always true
+    pub fn is_tautology(&self) -> bool {
+        self.literals
+            .iter()
+            .any(|&literal| self.literals.contains(&!literal))
+    }
+
+    // Sorts the literals by variable, A before !A, and removes the repeated ones
+    pub fn normalize(&mut self) {
+        self.literals.sort();
+        self.literals.dedup();
+    }
+
+    // True when every literal of self is in other, so other holds whenever self does
+    pub fn subsumes(&self, other: &Clause) -> bool {
+        self.literals.len() <= other.literals.len()
+            && self
+                .literals
+                .iter()
+                .all(|literal| other.literals.contains(literal))
+    }
+
+    // values[var] is the value of each variable
+    pub fn evaluate(&self, values: &[bool]) -> bool {
+        self.literals
+            .iter()
+            .any(|literal| literal.value(values[literal.var]))
+    }
+}
+
+impl FromIterator<Literal> for Clause {
+    fn from_iter<I: IntoIterator<Item = Literal>>(iter: I) -> Self {
+        Clause::new(iter.into_iter().collect())
+    }
+}
+
+/*
+    A formula in conjunctive normal form: a conjunction of clauses over numbered variables.
+    variables()[i] is the name of variable i, and no clause at all is true.
+    Variables stay in the Cnf even when no clause uses them anymore,
+    so a model always gives a value to every variable of the formula it came from.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cnf {
+    variables: Vec<String>,
+    clauses: Vec<Clause>,
+}
+
+impl Cnf {
+    pub fn new() -> Self {
+        Cnf::default()
+    }
+
+    pub fn with_variables(variables: Vec<String>) -> Self {
+        Cnf {
+            variables,
+            clauses: Vec::new(),
+        }
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<usize> {
+        self.variables.iter().position(|var| var == name)
+    }
+
+    pub fn name(&self, var: usize) -> &str {
+        &self.variables[var]
+    }
+
+    // Index of the variable called name, added at the end if the Cnf doesn't have it yet
+    pub fn add_variable(&mut self, name: &str) -> usize {
+        match self.variable(name) {
+            Some(var) => var,
+            None => {
+                self.variables.push(name.to_string());
+                self.variables.len() - 1
+            }
+        }
+    }
+
+    pub fn clauses(&self) -> &[Clause] {
+        &self.clauses
+    }
+
+    pub fn len(&self) -> usize {
+        self.clauses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    pub fn add_clause(&mut self, clause: Clause) {
+        assert!(
+            clause
+                .literals()
+                .iter()
+                .all(|literal| literal.var() < self.variables.len()),
+            "Literal of a variable the Cnf doesn't have"
+        );
+        self.clauses.push(clause);
+    }
+
+    // Adds a clause of (variable, negated) literals, with the variables it is missing
+    pub fn add_named_clause(&mut self, literals: &[(String, bool)]) {
+        let clause: Clause = literals
+            .iter()
+            .map(|(name, negated)| Literal::new(self.add_variable(name), *negated))
+            .collect();
+        self.clauses.push(clause);
+    }
+
+    // Variables are numbered in sorted order of their names
+    pub fn from_named_clauses(clauses: &[Vec<(String, bool)>]) -> Self {
+        let mut variables: Vec<String> = clauses
+            .iter()
+            .flatten()
+            .map(|(name, _)| name.clone())
+            .collect();
+        variables.sort();
+        variables.dedup();
+        let mut cnf: Cnf = Cnf::with_variables(variables);
+        for clause in clauses {
+            cnf.add_named_clause(clause);
+        }
+        cnf
+    }
+
+    pub fn to_named_clauses(&self) -> Vec<Vec<(String, bool)>> {
+        self.clauses
+            .iter()
+            .map(|clause| {
+                clause
+                    .literals()
+                    .iter()
+                    .map(|literal| (self.name(literal.var()).to_string(), literal.is_negated()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /*
+        Reads a formula that is already an AND of OR-clauses of literals, in any association.
+        Constants are folded: a clause with 1 is dropped and 0 is removed from its clause.
+        Variables are numbered in sorted order, those of dropped clauses included.
+    */
+    pub fn from_expr(node: &ExprNode<bool>) -> Result<Self, ParseError> {
+        let mut variables: Vec<String> = Vec::new();
+        expr_variables(node, &mut variables);
+        variables.sort();
+        variables.dedup();
+
+        let mut cnf: Cnf = Cnf::with_variables(variables);
+        let mut conjuncts: Vec<&ExprNode<bool>> = Vec::new();
+        flatten(node, '&', &mut conjuncts);
+        for conjunct in conjuncts {
+            let mut disjuncts: Vec<&ExprNode<bool>> = Vec::new();
+            flatten(conjunct, '|', &mut disjuncts);
+            let mut clause: Clause = Clause::default();
+            let mut satisfied: bool = false;
+            for disjunct in disjuncts {
+                match literal_of(disjunct) {
+                    Some(Ok((name, negated))) => {
+                        clause.push(Literal::new(cnf.variable(name).unwrap(), negated))
+                    }
+                    Some(Err(value)) => satisfied |= value,
+                    None => {
+                        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+                            ExpressionEvaluator::<bool, BooleanOperations>::new();
+                        return Err(ParseError::NotCnf {
+                            snippet: evaluator.to_rpn(disjunct),
+                        });
+                    }
+                }
+            }
+            if !satisfied {
+                cnf.clauses.push(clause);
+            }
+        }
+        Ok(cnf)
+    }
+
+    pub fn from_rpn(formula: &str) -> Result<Self, ParseError> {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        Cnf::from_expr(&evaluator.parse(formula)?)
+    }
+
+    // Right associative, (A | (B | C)) & (D | E), as the tree method of conjunctive_normal_form
+    pub fn to_expr(&self) -> ExprNode<bool> {
+        clauses_to_expr(&self.to_named_clauses())
+    }
+
+    pub fn to_rpn(&self) -> String {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.to_rpn(&self.to_expr())
+    }
+
+    /*
+        Sorts and deduplicates the literals of every clause, then removes tautological clauses,
+        repeated clauses and clauses subsumed by a smaller one: A & (A | B) is A.
+        Clauses end up sorted by length, so an empty clause is the only one left.
+    */
+    pub fn normalize(&mut self) {
+        let mut clauses: Vec<Clause> = std::mem::take(&mut self.clauses);
+        for clause in clauses.iter_mut() {
+            clause.normalize();
+        }
+        clauses.retain(|clause| !clause.is_tautology());
+        clauses.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        clauses.dedup();
+
+        for clause in clauses {
+            if !self.clauses.iter().any(|kept| kept.subsumes(&clause)) {
+                self.clauses.push(clause);
+            }
+        }
+    }
+
+    // values[var] is the value of each variable
+    pub fn evaluate(&self, values: &[bool]) -> bool {
+        self.clauses.iter().all(|clause| clause.evaluate(values))
+    }
+
+    // Names values[var] after each variable, values() of the result is values again
+    pub fn assignment(&self, values: &[bool]) -> Assignment<bool> {
+        self.variables
+            .iter()
+            .cloned()
+            .zip(values.iter().copied())
+            .collect()
+    }
+}
+
+impl fmt::Display for Cnf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_rpn())
+    }
+}
+
+fn expr_variables(node: &ExprNode<bool>, variables: &mut Vec<String>) {
+    match node {
+        ExprNode::Const(_) => {}
+        ExprNode::Var(name) => variables.push(name.clone()),
+        ExprNode::UnaryOp(_, child) => expr_variables(child, variables),
+        ExprNode::BinaryOp(_, left, right) => {
+            expr_variables(left, variables);
+            expr_variables(right, variables);
+        }
+    }
+}
+
+// Operands of a chain of op in any association, left to right
+pub(crate) fn flatten<'a>(
+    node: &'a ExprNode<bool>,
+    op: char,
+    operands: &mut Vec<&'a ExprNode<bool>>,
+) {
+    match node {
+        ExprNode::BinaryOp(node_op, left, right) if *node_op == op => {
+            flatten(left, op, operands);
+            flatten(right, op, operands);
+        }
+        _ => operands.push(node),
+    }
+}
+
+// A literal as (variable, negated), a constant as its value, None for anything else
+fn literal_of(node: &ExprNode<bool>) -> Option<Result<(&str, bool), bool>> {
+    match node {
+        ExprNode::Var(name) => Some(Ok((name, false))),
+        ExprNode::Const(value) => Some(Err(*value)),
+        ExprNode::UnaryOp('!', child) => match child.as_ref() {
+            ExprNode::Var(name) => Some(Ok((name, true))),
+            ExprNode::Const(value) => Some(Err(!value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::truth_table::generate_truth_table;
+
+    #[test]
+    fn test_literal() {
+        let a: Literal = Literal::positive(3);
+        assert_eq!(!a, Literal::negative(3));
+        assert_eq!(!!a, a);
+        assert_eq!(a.code(), 6);
+        assert_eq!((!a).code(), a.code() ^ 1);
+        assert_eq!(Literal::from_code(7), !a);
+        assert!(a.value(true) && (!a).value(false));
+        assert!(a < !a && !a < Literal::positive(4));
+    }
+
+    #[test]
+    fn test_rpn() {
+        let cnf: Cnf = Cnf::from_rpn("AB!C||A!BC||&").unwrap();
+        assert_eq!(cnf.variables(), ["A", "B", "C"]);
+        assert_eq!(cnf.len(), 2);
+        assert_eq!(
+            cnf.clauses()[0].literals(),
+            [
+                Literal::positive(0),
+                Literal::negative(1),
+                Literal::positive(2)
+            ]
+        );
+        assert_eq!(cnf.to_rpn(), "AB!C||A!BC||&");
+        assert!(cnf.evaluate(&[false, false, false]));
+        assert!(!cnf.evaluate(&[false, true, false]));
+
+        // Any association of & and |, constants folded
+        let cnf: Cnf = Cnf::from_rpn("AB|C|D1|&A0|&").unwrap();
+        assert_eq!(cnf.variables(), ["A", "B", "C", "D"]);
+        assert_eq!(cnf.to_rpn(), "ABC||A&");
+        assert_eq!(Cnf::from_rpn("1").unwrap().to_rpn(), "1");
+        assert_eq!(Cnf::from_rpn("A0&").unwrap().to_rpn(), "0");
+        assert_eq!(
+            Cnf::from_rpn("reset_n x12!|").unwrap().to_string(),
+            "reset_n x12!|"
+        );
+
+        assert_eq!(
+            Cnf::from_rpn("AB&C|").unwrap_err(),
+            ParseError::NotCnf {
+                snippet: "AB&".to_string()
+            }
+        );
+        assert_eq!(
+            Cnf::from_rpn("AB|!").unwrap_err(),
+            ParseError::NotCnf {
+                snippet: "AB|!".to_string()
+            }
+        );
+        assert!(Cnf::from_rpn("AB>").is_err());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut cnf: Cnf = Cnf::from_rpn("CBA||BA!|&A!A|&AC|&BA|C|&A&").unwrap();
+        cnf.normalize();
+        // A!A| is a tautology, ABC and AC are subsumed by A, which is then first
+        assert_eq!(cnf.to_rpn(), "AA!B|&");
+        assert_eq!(cnf.variables(), ["A", "B", "C"]);
+
+        let mut cnf: Cnf = Cnf::from_rpn("AB|A!&A&0&").unwrap();
+        cnf.normalize();
+        assert_eq!(cnf.len(), 1);
+        assert!(cnf.clauses()[0].is_empty());
+
+        let mut clause: Clause = [2, 0, 2, 1].into_iter().map(Literal::positive).collect();
+        clause.normalize();
+        assert_eq!(clause.len(), 3);
+        assert!(Clause::new(vec![Literal::positive(1)]).subsumes(&clause));
+        assert!(!clause.subsumes(&Clause::new(vec![Literal::positive(1)])));
+    }
+
+    #[test]
+    fn test_equivalence() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        for formula in ["AB|C&!", "AB^C=", "AB>C>D|", "AB|A!B!|&C|"] {
+            let mut cnf: Cnf = evaluator.cnf_formula(formula).unwrap();
+            let expected = generate_truth_table(formula, &mut evaluator).unwrap();
+            let table = generate_truth_table(&cnf.to_rpn(), &mut evaluator).unwrap();
+            assert_eq!(table, expected, "{}", formula);
+            cnf.normalize();
+            for (row, (assignment, result)) in expected.rows().enumerate() {
+                assert_eq!(cnf.evaluate(&assignment), result, "{} row {}", formula, row);
+            }
+        }
+    }
+
+    #[test]
+    fn test_named_clauses() {
+        let clauses: Vec<Vec<(String, bool)>> = vec![
+            vec![("B".to_string(), true), ("A".to_string(), false)],
+            vec![("C".to_string(), false)],
+        ];
+        let mut cnf: Cnf = Cnf::from_named_clauses(&clauses);
+        assert_eq!(cnf.variables(), ["A", "B", "C"]);
+        assert_eq!(cnf.to_named_clauses(), clauses);
+        cnf.add_named_clause(&[("D".to_string(), true)]);
+        assert_eq!(cnf.variable("D"), Some(3));
+        assert_eq!(cnf.to_rpn(), "B!A|CD!&&");
+    }
+}
@@ -1,33 +1,61 @@
 use crate::aux::check_only_vars;
+use crate::aux::expr_arena::{DagNode, ExprArena, NodeId};
 use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
+use crate::aux::lexer::{join_rpn, rpn_operand};
+use crate::aux::ParseError;
+use crate::boolean_operations::cnf::clause::Cnf;
 use crate::boolean_operations::BooleanOperations;
 use crate::truth_table::generate_truth_table;
+use std::collections::{HashMap, HashSet};
 
 impl ExpressionEvaluator<bool, BooleanOperations> {
-    pub fn conjunctive_normal_form(&mut self, formula: &str, truth_table: Option<bool>) -> String {
-        if truth_table.unwrap() == true {
-            self.derive_cnf_from_truth_table(formula).unwrap()
+    pub fn conjunctive_normal_form(
+        &mut self,
+        formula: &str,
+        truth_table: Option<bool>,
+    ) -> Result<String, ParseError> {
+        // The truth table method unless asked otherwise, like disjunctive_normal_form
+        if truth_table.unwrap_or(true) {
+            self.derive_cnf_from_truth_table(formula)
         } else {
-            let tree: ExprNode<bool> = self
-                .build_tree(formula, check_only_vars(formula), None)
-                .expect("Failed to build tree");
+            // Constants can be mixed with variables, they are folded into the clauses
+            let tree: ExprNode<bool> = self.parse(formula)?;
 
             // Convert the tree to NNF then distribute it to form cnf
             let nnf_tree = self.to_nnf(tree);
             let cnf_tree = self.to_cnf(nnf_tree);
 
-            self.to_rpn(&cnf_tree)
+            Ok(self.to_rpn(&cnf_tree))
         }
     }
 
-    pub fn derive_cnf_from_truth_table(&mut self, formula: &str) -> Result<String, String> {
+    /*
+        Same clauses as the tree method of conjunctive_normal_form, as a Cnf
+        numbering every variable of the formula in sorted order.
+    */
+    pub fn cnf_formula(&self, formula: &str) -> Result<Cnf, ParseError> {
+        let tree: ExprNode<bool> = self.parse(formula)?;
+        let mut variables: Vec<String> = Vec::new();
+        self.collect_variables(&tree, &mut variables);
+        variables.sort();
+        variables.dedup();
+
+        let nnf_tree = self.to_nnf(tree);
+        let mut cnf: Cnf = Cnf::with_variables(variables);
+        for clause in self.cnf_clauses(&nnf_tree) {
+            cnf.add_named_clause(&clause);
+        }
+        Ok(cnf)
+    }
+
+    pub fn derive_cnf_from_truth_table(&mut self, formula: &str) -> Result<String, ParseError> {
         // Generate the truth table
         let truth_table = generate_truth_table(formula, self)?;
 
         // Collect clauses for rows where the formula evaluates to false
         let mut clauses: Vec<String> = Vec::new();
 
-        for (assignment, result) in truth_table.rows {
+        for (assignment, result) in truth_table.rows() {
             if !result {
                 // Create a clause for this row
                 let mut clause: Vec<String> = Vec::new();
@@ -35,15 +63,22 @@ impl ExpressionEvaluator<bool, BooleanOperations> {
                 for (var, value) in truth_table.variables.iter().zip(assignment) {
                     if value {
                         // If the variable is true, add its negation
-                        clause.push(format!("{}!", var));
+                        clause.push(format!("{}!", rpn_operand(var)));
                     } else {
                         // If the variable is false, add the variable itself
-                        clause.push(var.to_string());
+                        clause.push(rpn_operand(var));
                     }
                 }
 
+                // Without variables the only row is an empty clause, the formula is always false
+                if clause.is_empty() {
+                    return Ok("0".to_string());
+                }
+
                 // Combine the literals in the clause with the correct number of | operators
-                let mut rpn_clause = clause.join("");
+                let mut rpn_clause = clause
+                    .iter()
+                    .fold(String::new(), |rpn, literal| join_rpn(&rpn, literal));
                 for _ in 1..clause.len() {
                     rpn_clause.push('|');
                 }
@@ -52,102 +87,182 @@ impl ExpressionEvaluator<bool, BooleanOperations> {
             }
         }
         /*
-            If the formula is a tautology, return it as is
+            A tautology has no clause, its CNF is the constant 1
             https://en.wikipedia.org/wiki/Tautology_(logic)
         */
         if clauses.is_empty() {
-            return Ok(formula.to_string());
+            return Ok("1".to_string());
         }
         // Combine all clauses with AND
-        let mut cnf = clauses.join("");
+        let mut cnf = clauses
+            .iter()
+            .fold(String::new(), |rpn, clause| join_rpn(&rpn, clause));
         for _ in 1..clauses.len() {
             cnf.push('&');
         }
 
-        Ok(cnf)
+        Ok(cnf.trim_end().to_string())
     }
 
-    fn to_cnf(&self, node: ExprNode<bool>) -> ExprNode<bool> {
-        match node {
-            // Base cases: constants, variables, and negations
-            ExprNode::Const(_) | ExprNode::Var(_) | ExprNode::UnaryOp(_, _) => node,
-
-            // Handle binary operators
-            ExprNode::BinaryOp(op, left, right) => {
-                let left_cnf = self.to_cnf(*left);
-                let right_cnf = self.to_cnf(*right);
+    /*
+        CNF of a formula stored in an arena: NNF first, then | is distributed over &.
+        Subformulas shared in the DAG are converted once.
+    */
+    pub fn to_cnf_dag(&self, arena: &mut ExprArena<bool>, root: NodeId) -> NodeId {
+        let nnf: NodeId = self.to_nnf_dag(arena, root);
+        let mut memo: HashMap<NodeId, NodeId> = HashMap::new();
+        self.cnf_dag(arena, nnf, &mut memo)
+    }
 
-                match op {
-                    '&' => {
-                        // Flatten nested conjunctions into a single top-level conjunction
-                        self.flatten_conjunction(left_cnf, right_cnf)
-                    }
-                    '|' => {
-                        // Flatten nested disjunctions into a single top-level disjunction
-                        self.flatten_disjunction(left_cnf, right_cnf)
-                    }
-                    _ => ExprNode::BinaryOp(op, Box::new(left_cnf), Box::new(right_cnf)),
+    fn cnf_dag(
+        &self,
+        arena: &mut ExprArena<bool>,
+        id: NodeId,
+        memo: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if let Some(&done) = memo.get(&id) {
+            return done;
+        }
+        let result: NodeId = match *arena.node(id) {
+            DagNode::BinaryOp(op @ ('&' | '|'), left, right) => {
+                let left: NodeId = self.cnf_dag(arena, left, memo);
+                let right: NodeId = self.cnf_dag(arena, right, memo);
+                if op == '&' {
+                    arena.binary('&', left, right)
+                } else {
+                    self.distribute_dag(arena, left, right)
                 }
             }
+            // Literals
+            _ => id,
+        };
+        memo.insert(id, result);
+        result
+    }
+
+    // (A & B) | C => (A | C) & (B | C), for operands already in CNF
+    fn distribute_dag(&self, arena: &mut ExprArena<bool>, left: NodeId, right: NodeId) -> NodeId {
+        match (arena.node(left).clone(), arena.node(right).clone()) {
+            (DagNode::BinaryOp('&', first, second), _) => {
+                let first: NodeId = self.distribute_dag(arena, first, right);
+                let second: NodeId = self.distribute_dag(arena, second, right);
+                arena.binary('&', first, second)
+            }
+            (_, DagNode::BinaryOp('&', first, second)) => {
+                let first: NodeId = self.distribute_dag(arena, left, first);
+                let second: NodeId = self.distribute_dag(arena, left, second);
+                arena.binary('&', first, second)
+            }
+            _ => arena.binary('|', left, right),
         }
     }
 
-    // Helper function to flatten conjunctions
-    fn flatten_conjunction(&self, left: ExprNode<bool>, right: ExprNode<bool>) -> ExprNode<bool> {
-        match (left, right) {
-            // If both sides are conjunctions, merge them
-            (
-                ExprNode::BinaryOp('&', left_and, right_and),
-                ExprNode::BinaryOp('&', left_and2, right_and2),
-            ) => ExprNode::BinaryOp(
-                '&',
-                Box::new(self.flatten_conjunction(*left_and, *right_and)),
-                Box::new(self.flatten_conjunction(*left_and2, *right_and2)),
-            ),
-            // If one side is a conjunction, merge the other side into it
-            (ExprNode::BinaryOp('&', left_and, right_and), other)
-            | (other, ExprNode::BinaryOp('&', left_and, right_and)) => ExprNode::BinaryOp(
-                '&',
-                Box::new(self.flatten_conjunction(*left_and, *right_and)),
-                Box::new(other),
-            ),
-            // If neither side is a conjunction, create a new conjunction
-            (left, right) => ExprNode::BinaryOp('&', Box::new(left), Box::new(right)),
+    /*
+        Expects a formula in NNF. Distributes | over & to get an AND of OR-clauses of literals,
+        then removes repeated literals, tautological clauses (A | !A) and repeated clauses.
+        Constants are folded away: no clause left is 1 and an empty clause is 0.
+    */
+    fn to_cnf(&self, node: ExprNode<bool>) -> ExprNode<bool> {
+        // Each clause is sorted once to be compared, the first of equal ones is kept in place
+        let mut seen: HashSet<Vec<(String, bool)>> = HashSet::new();
+        let mut clauses: Vec<Vec<(String, bool)>> = Vec::new();
+        for clause in self.cnf_clauses(&node) {
+            let mut sorted: Vec<(String, bool)> = clause.clone();
+            sorted.sort();
+            if seen.insert(sorted) {
+                clauses.push(clause);
+            }
         }
+
+        clauses_to_expr(&clauses)
     }
 
-    // Helper function to flatten disjunctions
-    fn flatten_disjunction(&self, left: ExprNode<bool>, right: ExprNode<bool>) -> ExprNode<bool> {
-        match (left, right) {
-            // If both sides are disjunctions, merge them
-            (
-                ExprNode::BinaryOp('|', left_or, right_or),
-                ExprNode::BinaryOp('|', left_or2, right_or2),
-            ) => ExprNode::BinaryOp(
-                '|',
-                Box::new(self.flatten_disjunction(*left_or, *right_or)),
-                Box::new(self.flatten_disjunction(*left_or2, *right_or2)),
-            ),
-            // If one side is a disjunction, merge the other side into it
-            (ExprNode::BinaryOp('|', left_or, right_or), other)
-            | (other, ExprNode::BinaryOp('|', left_or, right_or)) => ExprNode::BinaryOp(
-                '|',
-                Box::new(self.flatten_disjunction(*left_or, *right_or)),
-                Box::new(other),
-            ),
-            // If neither side is a disjunction, create a new disjunction
-            (left, right) => ExprNode::BinaryOp('|', Box::new(left), Box::new(right)),
+    // Clauses of a formula in NNF as lists of (variable, negated), no clause at all means true
+    fn cnf_clauses(&self, node: &ExprNode<bool>) -> Vec<Vec<(String, bool)>> {
+        match node {
+            ExprNode::Const(true) => vec![],
+            ExprNode::Const(false) => vec![vec![]],
+            ExprNode::Var(name) => vec![vec![(name.clone(), false)]],
+            ExprNode::UnaryOp('!', child) => match child.as_ref() {
+                ExprNode::Const(value) => self.cnf_clauses(&ExprNode::Const(!value)),
+                ExprNode::Var(name) => vec![vec![(name.clone(), true)]],
+                _ => unreachable!("Negation of a non literal, the formula is not in NNF"),
+            },
+            ExprNode::BinaryOp('&', left, right) => {
+                let mut clauses = self.cnf_clauses(left);
+                clauses.extend(self.cnf_clauses(right));
+                clauses
+            }
+            // (A & B) | (C & D) => (A | C) & (A | D) & (B | C) & (B | D)
+            ExprNode::BinaryOp('|', left, right) => {
+                let right_clauses = self.cnf_clauses(right);
+                let mut clauses: Vec<Vec<(String, bool)>> = Vec::new();
+                for left_clause in self.cnf_clauses(left) {
+                    for right_clause in &right_clauses {
+                        let mut clause: Vec<(String, bool)> = left_clause.clone();
+                        for literal in right_clause {
+                            if !clause.contains(literal) {
+                                clause.push(literal.clone());
+                            }
+                        }
+                        // A clause with A and !A is always true
+                        let tautology: bool = clause
+                            .iter()
+                            .any(|(name, negated)| clause.contains(&(name.clone(), !negated)));
+                        if !tautology {
+                            clauses.push(clause);
+                        }
+                    }
+                }
+                clauses
+            }
+            _ => unreachable!("The formula is not in NNF"),
         }
     }
 }
 
+/*
+    Builds the formula of a list of clauses of (variable, negated) literals.
+    Written right associative, (A | (B | C)) & (D | E) is ABC||DE|&.
+    No clause at all is 1 and an empty clause makes it 0.
+*/
+pub(crate) fn clauses_to_expr(clauses: &[Vec<(String, bool)>]) -> ExprNode<bool> {
+    if clauses.iter().any(|clause| clause.is_empty()) {
+        return ExprNode::Const(false);
+    }
+    let literal = |(name, negated): &(String, bool)| {
+        let var: ExprNode<bool> = ExprNode::Var(name.clone());
+        if *negated {
+            ExprNode::UnaryOp('!', Box::new(var))
+        } else {
+            var
+        }
+    };
+    let clause = |clause: &Vec<(String, bool)>| {
+        clause
+            .iter()
+            .rev()
+            .map(literal)
+            .reduce(|right, left| ExprNode::BinaryOp('|', Box::new(left), Box::new(right)))
+            .unwrap()
+    };
+    clauses
+        .iter()
+        .rev()
+        .map(clause)
+        .reduce(|right, left| ExprNode::BinaryOp('&', Box::new(left), Box::new(right)))
+        .unwrap_or(ExprNode::Const(true))
+}
+
 pub fn run_conjunctive_normal_form() {
     let mut boolean_evaluation: ExpressionEvaluator<bool, BooleanOperations> =
         ExpressionEvaluator::<bool, BooleanOperations>::new();
     println!("\n\tRunning conjunctive_normal_form function\n");
     let formula = "ABCD&|&";
     println!("Original formula: {}", formula);
-    let cnf = boolean_evaluation.conjunctive_normal_form(formula, Some(true));
+    let cnf = boolean_evaluation
+        .conjunctive_normal_form(formula, Some(true))
+        .unwrap();
     println!("Conjunctive Normal Form {}", cnf);
     println!(
         "Formula {}",
@@ -162,6 +277,7 @@ pub fn run_conjunctive_normal_form() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aux::Assignment;
     #[test]
 
     fn test_not_truth() {
@@ -174,75 +290,99 @@ mod tests {
         The subject's implementation is more efficient
         The truth table method is easier to implement since I already have the truth table function
         */
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("A", Some(false)),
-            "A"
-        );
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AA&AA&&", Some(false)),
-            "AA&AA&&"
-        );
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB&!", Some(false)),
-            "A!B!|"
-        );
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|!", Some(false)),
-            "A!B!&"
-        );
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|C&", Some(false)),
-            "AB|C&"
-        );
-        /*
-         The subject writes it as ABC||| but that's just moving the formula from left associative to right associative
-         (((A | B) | C) | D) => (A | (B | (C | D)))
-         That's not what CNF is about, CNF is about distributing OR over AND
-        */
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|C|D|", Some(false)),
-            "AB|C|D|"
-        );
-        /*
-        Formula of AB|C&! is (A or B) and (not C)
-        The CNF of this formula is C!A!|C!B!|&
-        Which is equivalent to (not C or not A) and (not C or not B)
-        */
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|C&!", Some(false)),
-            "A!B!&C!|"
-        );
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("ABCD&|&", Some(false)),
-            "ABCD&|&"
-        );
-        /*
-        The subject writes it as ABCD&&& but that's just moving the formula from left associative to right associative
-        (((A & B) & C) & D) => (A & (B & (C & D)))
-        That's not what CNF is about, CNF is about distributing OR over AND
-        */
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB&C&D&", Some(false)),
-            "AB&C&D&"
-        );
-        /*
-        Formula for AB&!C!| is (A and B) or (not C)
-        The CNF of this formula is A!B!|C!|
-            Which is equivalent to (not A or not B) or (not C)
-            Subject gives us A!B!C!||
-            Which is equivalent to (not A) or (not B or not C)
+        let cases = [
+            ("A", "A"),
+            // Repeated clauses are kept once
+            ("AA&AA&&", "A"),
+            // in any order of their literals, the first one is kept where it was
+            ("CAB|&BA|&", "CAB|&"),
+            ("AB&!", "A!B!|"),
+            ("AB|!", "A!B!&"),
+            ("AB|C&", "AB|C&"),
+            // Clauses and conjunctions are written right associative, like the subject
+            ("AB|C|D|", "ABCD|||"),
+            ("AB&C&D&", "ABCD&&&"),
+            /*
+            Formula of AB|C&! is (A or B) and (not C)
+            The CNF of this formula is A!C!|B!C!|&
+            Which is equivalent to (not A or not C) and (not B or not C)
             */
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB&!C!|", Some(false)),
-            "A!B!|C!|"
-        );
-        /*
-        Formula AB|!C!&  is (A or B) and (not C), subject is again just moving the association to the right, which is not CNF
-        */
-        assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|!C!&", Some(false)),
-            "A!B!&C!&"
-        );
+            ("AB|C&!", "A!C!|B!C!|&"),
+            // A & (B | (C & D)) => A & (B | C) & (B | D)
+            ("ABCD&|&", "ABC|BD|&&"),
+            ("AB&!C!|", "A!B!C!||"),
+            ("AB|!C!&", "A!B!C!&&"),
+            // (A | B) & (A | !B) & ... the tautological clauses (A | !A) are removed
+            ("AB&A!B!&|", "AB!|BA!|&"),
+            ("AA!|", "1"),
+            ("A0&", "0"),
+            ("A0|B1|&", "A"),
+        ];
+        for (formula, expected) in cases {
+            assert_eq!(
+                boolean_evaluation
+                    .conjunctive_normal_form(formula, Some(false))
+                    .unwrap(),
+                expected,
+                "{}",
+                formula
+            );
+        }
+    }
+
+    // Every clause is an OR of literals and the formula is an AND of clauses
+    fn is_cnf(node: &ExprNode<bool>, inside_clause: bool) -> bool {
+        match node {
+            ExprNode::Var(_) | ExprNode::Const(_) => true,
+            ExprNode::UnaryOp('!', child) => matches!(child.as_ref(), ExprNode::Var(_)),
+            ExprNode::BinaryOp('|', left, right) => is_cnf(left, true) && is_cnf(right, true),
+            ExprNode::BinaryOp('&', left, right) => {
+                !inside_clause && is_cnf(left, false) && is_cnf(right, false)
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_tree_method_equivalence() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formulas = [
+            "AB&!",
+            "AB|!C!&",
+            "AB^C=",
+            "AB=C=D=",
+            "AB>C>D|!",
+            "AB&CD&|EF&|",
+            "AB|C&!D^",
+            "A1^B0=|",
+            "reset_n x12|!A&",
+        ];
+        for formula in formulas {
+            let cnf = evaluator
+                .conjunctive_normal_form(formula, Some(false))
+                .unwrap();
+            assert!(
+                is_cnf(&evaluator.parse(&cnf).unwrap(), false),
+                "{} => {}",
+                formula,
+                cnf
+            );
+            // Removed clauses can drop variables, so compare with the variables of the formula
+            let table = generate_truth_table(formula, &mut evaluator).unwrap();
+            let tree = evaluator.parse(&cnf).unwrap();
+            for (assignment, result) in table.rows() {
+                let values: Assignment<bool> =
+                    table.variables.iter().cloned().zip(assignment).collect();
+                assert_eq!(
+                    evaluator.evaluate_assignment(&tree, &values).unwrap(),
+                    result,
+                    "{} => {}",
+                    formula,
+                    cnf
+                );
+            }
+        }
     }
     #[test]
     fn test_second_method_with_truth_table() {
@@ -256,59 +396,120 @@ mod tests {
             The truth table method is easier to implement since I already have the truth table function
         */
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("A", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("A", Some(true))
+                .unwrap(),
             "A"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AA&AA&&", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AA&AA&&", Some(true))
+                .unwrap(),
             "A"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB&!", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB&!", Some(true))
+                .unwrap(),
             "A!B!|"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|!", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB|!", Some(true))
+                .unwrap(),
             "AB!|A!B|A!B!|&&"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|C&", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB|C&", Some(true))
+                .unwrap(),
             "ABC||ABC!||AB!C||A!BC||A!B!C||&&&&"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|C|D|", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB|C|D|", Some(true))
+                .unwrap(),
             "ABCD|||"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|C&!", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB|C&!", Some(true))
+                .unwrap(),
             "AB!C!||A!BC!||A!B!C!||&&"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("ABCD&|&", Some(true)),
+            boolean_evaluation.conjunctive_normal_form("ABCD&|&", Some(true)).unwrap(),
             "ABCD|||ABCD!|||ABC!D|||ABC!D!|||AB!CD|||AB!CD!|||AB!C!D|||AB!C!D!|||A!BCD|||A!BCD!|||A!BC!D|||&&&&&&&&&&"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB&C&D&", Some(true)),
+            boolean_evaluation.conjunctive_normal_form("AB&C&D&", Some(true)).unwrap(),
             "ABCD|||ABCD!|||ABC!D|||ABC!D!|||AB!CD|||AB!CD!|||AB!C!D|||AB!C!D!|||A!BCD|||A!BCD!|||A!BC!D|||A!BC!D!|||A!B!CD|||A!B!CD!|||A!B!C!D|||&&&&&&&&&&&&&&"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB&!C!|", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB&!C!|", Some(true))
+                .unwrap(),
             "A!B!C!||"
         );
         assert_eq!(
-            boolean_evaluation.conjunctive_normal_form("AB|!C!&", Some(true)),
+            boolean_evaluation
+                .conjunctive_normal_form("AB|!C!&", Some(true))
+                .unwrap(),
             "ABC!||AB!C||AB!C!||A!BC||A!BC!||A!B!C||A!B!C!||&&&&&&"
         );
+        assert_eq!(
+            boolean_evaluation
+                .conjunctive_normal_form("10>", Some(true))
+                .unwrap(),
+            "0"
+        );
+        assert_eq!(
+            boolean_evaluation
+                .conjunctive_normal_form("AB^AB=|", Some(true))
+                .unwrap(),
+            "1"
+        );
+        assert_eq!(
+            boolean_evaluation
+                .conjunctive_normal_form("AB&!", None)
+                .unwrap(),
+            "A!B!|"
+        );
     }
     #[test]
     fn test_result_truth_table() {
         let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
             ExpressionEvaluator::<bool, BooleanOperations>::new();
         let formula = "AB|!C!&";
-        let cnf = evaluator.conjunctive_normal_form(formula, Some(true));
+        let cnf = evaluator
+            .conjunctive_normal_form(formula, Some(true))
+            .unwrap();
         assert_eq!(
             generate_truth_table(formula, &mut evaluator).unwrap(),
             generate_truth_table(&cnf, &mut evaluator).unwrap()
         );
     }
+    #[test]
+    fn test_identifiers() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formula = "reset_n x12|!A&";
+        let cnf = evaluator
+            .conjunctive_normal_form(formula, Some(true))
+            .unwrap();
+        assert_eq!(
+            cnf,
+            "A reset_n x12||A reset_n x12!||A reset_n!x12||A reset_n!x12!||A!reset_n x12!||A!reset_n!x12||A!reset_n!x12!||&&&&&&"
+        );
+        assert_eq!(
+            generate_truth_table(formula, &mut evaluator).unwrap(),
+            generate_truth_table(&cnf, &mut evaluator).unwrap()
+        );
+        assert_eq!(
+            evaluator
+                .conjunctive_normal_form(formula, Some(false))
+                .unwrap(),
+            "reset_n!x12!A&&"
+        );
+    }
 }
@@ -0,0 +1,323 @@
+use crate::boolean_operations::cnf::clause::{Clause, Cnf, Literal};
+use std::collections::HashMap;
+use std::fmt;
+
+/*
+    Errors raised while reading a DIMACS file.
+    Lines are numbered from 1, as a text editor shows them.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum DimacsError {
+    MissingHeader,
+    InvalidHeader {
+        line: usize,
+    },
+    InvalidToken {
+        line: usize,
+        snippet: String,
+    },
+    VariableOutOfRange {
+        line: usize,
+        var: usize,
+        num_variables: usize,
+    },
+    ClauseCount {
+        expected: usize,
+        found: usize,
+    },
+    DuplicateName {
+        line: usize,
+        name: String,
+    },
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DimacsError::MissingHeader => write!(f, "Error: Missing 'p cnf' header"),
+            DimacsError::InvalidHeader { line } => {
+                write!(f, "Error: Invalid 'p cnf' header on line {}", line)
+            }
+            DimacsError::InvalidToken { line, snippet } => {
+                write!(f, "Error: Invalid literal '{}' on line {}", snippet, line)
+            }
+            DimacsError::VariableOutOfRange {
+                line,
+                var,
+                num_variables,
+            } => write!(
+                f,
+                "Error: Variable {} on line {} is out of range, the header declares {}",
+                var, line, num_variables
+            ),
+            DimacsError::ClauseCount { expected, found } => write!(
+                f,
+                "Error: The header declares {} clauses, found {}",
+                expected, found
+            ),
+            DimacsError::DuplicateName { line, name } => {
+                write!(f, "Error: Name '{}' on line {} is already used", name, line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DimacsError {}
+
+impl Cnf {
+    /*
+        Writes the Cnf in the DIMACS 'p cnf' format. Variable i is written as i + 1
+        and its name goes in a 'c <number> <name>' comment before the header.
+    */
+    pub fn to_dimacs(&self) -> String {
+        let mut dimacs: String = String::new();
+        for (var, name) in self.variables().iter().enumerate() {
+            dimacs.push_str(&format!("c {} {}\n", var + 1, name));
+        }
+        dimacs.push_str(&format!("p cnf {} {}\n", self.num_variables(), self.len()));
+        for clause in self.clauses() {
+            for literal in clause.literals() {
+                dimacs.push_str(&format!("{} ", dimacs_literal(*literal)));
+            }
+            dimacs.push_str("0\n");
+        }
+        dimacs
+    }
+
+    /*
+        Reads a DIMACS 'p cnf' file. Names come from the 'c <number> <name>' comments,
+        any other comment is ignored, and unnamed variables are called x<number>.
+        Clauses can span several lines, the 0 ending the last one can be left out
+        and a line with % ends the file, as in the SATLIB benchmarks.
+    */
+    pub fn from_dimacs(input: &str) -> Result<Self, DimacsError> {
+        let mut header: Option<(usize, usize)> = None;
+        let mut names: HashMap<usize, (String, usize)> = HashMap::new();
+        let mut clauses: Vec<Clause> = Vec::new();
+        let mut clause: Clause = Clause::default();
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number: usize = index + 1;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.first() {
+                None => continue,
+                Some(&"%") => break,
+                Some(&"c") => {
+                    // Only comments that look like a name are read
+                    if let [_, var, name] = tokens[..] {
+                        if let Ok(var) = var.parse::<usize>() {
+                            names.insert(var, (name.to_string(), line_number));
+                        }
+                    }
+                    continue;
+                }
+                Some(&"p") => {
+                    if header.is_some() {
+                        return Err(DimacsError::InvalidHeader { line: line_number });
+                    }
+                    header = match tokens[..] {
+                        ["p", "cnf", num_variables, num_clauses] => {
+                            match (num_variables.parse(), num_clauses.parse()) {
+                                (Ok(num_variables), Ok(num_clauses)) => {
+                                    Some((num_variables, num_clauses))
+                                }
+                                _ => return Err(DimacsError::InvalidHeader { line: line_number }),
+                            }
+                        }
+                        _ => return Err(DimacsError::InvalidHeader { line: line_number }),
+                    };
+                    continue;
+                }
+                _ => {}
+            }
+
+            let (num_variables, _) = header.ok_or(DimacsError::MissingHeader)?;
+            for token in tokens {
+                let value: i64 = token.parse().map_err(|_| DimacsError::InvalidToken {
+                    line: line_number,
+                    snippet: token.to_string(),
+                })?;
+                if value == 0 {
+                    clauses.push(std::mem::take(&mut clause));
+                    continue;
+                }
+                let var: usize = value.unsigned_abs() as usize;
+                if var > num_variables {
+                    return Err(DimacsError::VariableOutOfRange {
+                        line: line_number,
+                        var,
+                        num_variables,
+                    });
+                }
+                clause.push(Literal::new(var - 1, value < 0));
+            }
+        }
+
+        let (num_variables, num_clauses) = header.ok_or(DimacsError::MissingHeader)?;
+        if !clause.is_empty() {
+            clauses.push(clause);
+        }
+        if clauses.len() != num_clauses {
+            return Err(DimacsError::ClauseCount {
+                expected: num_clauses,
+                found: clauses.len(),
+            });
+        }
+
+        let variables: Vec<String> = variable_names(&names, num_variables)?;
+        let mut cnf: Cnf = Cnf::with_variables(variables);
+        for clause in clauses {
+            cnf.add_clause(clause);
+        }
+        Ok(cnf)
+    }
+}
+
+/*
+    Names of the variables 1 to num_variables of a DIMACS file, from the comments
+    read as (name, line) by number. The first comment to use a name keeps it,
+    the others are errors, and unnamed variables are called x<number>.
+*/
+pub(crate) fn variable_names(
+    names: &HashMap<usize, (String, usize)>,
+    num_variables: usize,
+) -> Result<Vec<String>, DimacsError> {
+    let mut variables: Vec<Option<String>> = vec![None; num_variables];
+    let mut lines: Vec<(usize, usize)> = names
+        .iter()
+        .filter(|(&var, _)| 1 <= var && var <= num_variables)
+        .map(|(&var, (_, line))| (*line, var))
+        .collect();
+    lines.sort();
+    for (line, var) in lines {
+        let name: &String = &names[&var].0;
+        if variables.iter().flatten().any(|used| used == name) {
+            return Err(DimacsError::DuplicateName {
+                line,
+                name: name.clone(),
+            });
+        }
+        variables[var - 1] = Some(name.clone());
+    }
+
+    // x<number> unless a comment gave that name to another variable
+    let taken: Vec<String> = variables.iter().flatten().cloned().collect();
+    let variables: Vec<String> = variables
+        .into_iter()
+        .enumerate()
+        .map(|(var, name)| {
+            name.unwrap_or_else(|| {
+                let mut name: String = format!("x{}", var + 1);
+                while taken.contains(&name) {
+                    name.push('_');
+                }
+                name
+            })
+        })
+        .collect();
+    Ok(variables)
+}
+
+// Literal as written in DIMACS: the variable number, negative if negated
+pub fn dimacs_literal(literal: Literal) -> i64 {
+    let var: i64 = literal.var() as i64 + 1;
+    if literal.is_negated() {
+        -var
+    } else {
+        var
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aux::ExpressionEvaluator;
+    use crate::boolean_operations::BooleanOperations;
+
+    #[test]
+    fn test_write() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formula: String = evaluator
+            .conjunctive_normal_form("AB|C&!", Some(false))
+            .unwrap();
+        let cnf: Cnf = Cnf::from_rpn(&formula).unwrap();
+        assert_eq!(
+            cnf.to_dimacs(),
+            "c 1 A\nc 2 B\nc 3 C\np cnf 3 2\n-1 -3 0\n-2 -3 0\n"
+        );
+        assert_eq!(Cnf::from_dimacs(&cnf.to_dimacs()).unwrap(), cnf);
+
+        let cnf: Cnf = Cnf::from_rpn("reset_n x12!|0&").unwrap();
+        assert_eq!(
+            cnf.to_dimacs(),
+            "c 1 reset_n\nc 2 x12\np cnf 2 2\n1 -2 0\n0\n"
+        );
+        assert_eq!(Cnf::from_dimacs(&cnf.to_dimacs()).unwrap(), cnf);
+    }
+
+    #[test]
+    fn test_read() {
+        let input: &str = "c A small example\n\
+                           c 2 B\n\
+                           p cnf 3 3\n\
+                           1 -2\n\
+                           3 0 -1 0\n\
+                           \n\
+                           2 3\n\
+                           %\n\
+                           0\n";
+        let cnf: Cnf = Cnf::from_dimacs(input).unwrap();
+        assert_eq!(cnf.variables(), ["x1", "B", "x3"]);
+        assert_eq!(cnf.to_rpn(), "x1 B!x3||x1!B x3|&&");
+
+        // The default name of an unnamed variable is free for a comment to use
+        let cnf: Cnf = Cnf::from_dimacs("p cnf 2 1\nc 2 x1\n1 2 0\n").unwrap();
+        assert_eq!(cnf.variables(), ["x1_", "x1"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Cnf::from_dimacs("c nothing\n"),
+            Err(DimacsError::MissingHeader)
+        );
+        assert_eq!(
+            Cnf::from_dimacs("1 2 0\np cnf 2 1\n"),
+            Err(DimacsError::MissingHeader)
+        );
+        assert_eq!(
+            Cnf::from_dimacs("p cnf two 1\n"),
+            Err(DimacsError::InvalidHeader { line: 1 })
+        );
+        assert_eq!(
+            Cnf::from_dimacs("p cnf 2 1\n1 B 0\n"),
+            Err(DimacsError::InvalidToken {
+                line: 2,
+                snippet: "B".to_string()
+            })
+        );
+        assert_eq!(
+            Cnf::from_dimacs("p cnf 2 1\n1 -3 0\n"),
+            Err(DimacsError::VariableOutOfRange {
+                line: 2,
+                var: 3,
+                num_variables: 2
+            })
+        );
+        assert_eq!(
+            Cnf::from_dimacs("p cnf 2 2\n1 -2 0\n"),
+            Err(DimacsError::ClauseCount {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            Cnf::from_dimacs("c 1 A\nc 2 A\np cnf 2 0\n"),
+            Err(DimacsError::DuplicateName {
+                line: 2,
+                name: "A".to_string()
+            })
+        );
+    }
+}
@@ -1,3 +1,9 @@
+pub mod clause;
 pub mod cnf;
+pub mod dimacs;
+pub mod tseitin;
 
-pub use cnf::run_conjunctive_normal_form;
\ No newline at end of file
+pub use clause::{Clause, Cnf, Literal};
+pub use cnf::run_conjunctive_normal_form;
+pub use dimacs::DimacsError;
+pub use tseitin::TseitinCnf;
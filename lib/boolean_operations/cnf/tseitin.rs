@@ -0,0 +1,399 @@
+use crate::aux::expr_arena::{DagNode, ExprArena, NodeId};
+use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
+use crate::aux::ParseError;
+use crate::boolean_operations::cnf::clause::Cnf;
+use crate::boolean_operations::cnf::cnf::clauses_to_expr;
+use crate::boolean_operations::BooleanOperations;
+use std::collections::{HashMap, HashSet};
+
+/*
+    CNF that is satisfiable exactly when the formula it was encoded from is,
+    with one auxiliary variable per distinct subformula instead of distributing | over &.
+    Every auxiliary variable comes with the gate it stands for, written with the literals
+    of its operands, so models can be projected back onto the original variables.
+*/
+#[derive(Debug, Clone)]
+pub struct TseitinCnf {
+    pub clauses: Vec<Vec<(String, bool)>>,
+    // Variables of the original formula, sorted
+    pub variables: Vec<String>,
+    // Auxiliary variables in the order they were created, with their gate
+    pub definitions: Vec<(String, ExprNode<bool>)>,
+}
+
+impl TseitinCnf {
+    pub fn is_auxiliary(&self, name: &str) -> bool {
+        self.definitions.iter().any(|(aux, _)| aux == name)
+    }
+
+    // The subformula an auxiliary variable stands for, written with the original variables only
+    pub fn subformula(&self, name: &str) -> Option<ExprNode<bool>> {
+        let (_, gate) = self.definitions.iter().find(|(aux, _)| aux == name)?;
+        Some(self.expand(gate))
+    }
+
+    fn expand(&self, node: &ExprNode<bool>) -> ExprNode<bool> {
+        match node {
+            ExprNode::Var(name) => self
+                .subformula(name)
+                .unwrap_or_else(|| ExprNode::Var(name.clone())),
+            ExprNode::UnaryOp(op, child) => ExprNode::UnaryOp(*op, Box::new(self.expand(child))),
+            ExprNode::BinaryOp(op, left, right) => ExprNode::BinaryOp(
+                *op,
+                Box::new(self.expand(left)),
+                Box::new(self.expand(right)),
+            ),
+            ExprNode::Const(value) => ExprNode::Const(*value),
+        }
+    }
+
+    // Keeps only the values of the original variables of a model of the clauses
+    pub fn project(&self, model: &HashMap<String, bool>) -> HashMap<String, bool> {
+        model
+            .iter()
+            .filter(|(name, _)| self.variables.contains(name))
+            .map(|(name, value)| (name.clone(), *value))
+            .collect()
+    }
+
+    pub fn to_expr(&self) -> ExprNode<bool> {
+        clauses_to_expr(&self.clauses)
+    }
+
+    // The original variables come first, so a model of it projects onto its first variables
+    pub fn to_cnf(&self) -> Cnf {
+        let mut cnf: Cnf = Cnf::with_variables(self.variables.clone());
+        for (aux, _) in &self.definitions {
+            cnf.add_variable(aux);
+        }
+        for clause in &self.clauses {
+            cnf.add_named_clause(clause);
+        }
+        cnf
+    }
+}
+
+// Which implications of a gate are needed: x => gate, gate => x or both
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Polarity {
+    Positive,
+    Negative,
+    Both,
+}
+
+impl Polarity {
+    fn from_directions(positive: bool, negative: bool) -> Self {
+        match (positive, negative) {
+            (true, false) => Polarity::Positive,
+            (false, true) => Polarity::Negative,
+            _ => Polarity::Both,
+        }
+    }
+
+    fn flip(self) -> Self {
+        match self {
+            Polarity::Positive => Polarity::Negative,
+            Polarity::Negative => Polarity::Positive,
+            Polarity::Both => Polarity::Both,
+        }
+    }
+
+    fn positive(self) -> bool {
+        self != Polarity::Negative
+    }
+
+    fn negative(self) -> bool {
+        self != Polarity::Positive
+    }
+}
+
+type Literal = (String, bool);
+
+fn not(literal: &Literal) -> Literal {
+    (literal.0.clone(), !literal.1)
+}
+
+struct TseitinEncoder {
+    arena: ExprArena<bool>,
+    polarity_aware: bool,
+    // Names that can't be given to an auxiliary variable
+    taken: HashSet<String>,
+    next: usize,
+    literals: HashMap<NodeId, Literal>,
+    // Directions already encoded for a node, positive and negative
+    encoded: HashMap<NodeId, (bool, bool)>,
+    clauses: Vec<Vec<Literal>>,
+    definitions: Vec<(String, ExprNode<bool>)>,
+}
+
+impl TseitinEncoder {
+    fn fresh(&mut self) -> String {
+        loop {
+            let name: String = format!("_t{}", self.next);
+            self.next += 1;
+            if self.taken.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+
+    fn encode(
+        &mut self,
+        evaluator: &ExpressionEvaluator<bool, BooleanOperations>,
+        id: NodeId,
+        polarity: Polarity,
+    ) -> Literal {
+        // Registered connectives are first written with the core ones
+        let id: NodeId = evaluator.rewrite_connective_dag(&mut self.arena, id);
+        let node: DagNode<bool> = self.arena.node(id).clone();
+        let (left, right, op) = match node {
+            DagNode::Var(name) => return (name, false),
+            DagNode::UnaryOp('!', child) => {
+                return not(&self.encode(evaluator, child, polarity.flip()))
+            }
+            DagNode::Const(value) => {
+                if let Some(literal) = self.literals.get(&id) {
+                    return literal.clone();
+                }
+                let name: String = self.fresh();
+                self.clauses.push(vec![(name.clone(), !value)]);
+                self.definitions
+                    .push((name.clone(), ExprNode::Const(value)));
+                self.literals.insert(id, (name.clone(), false));
+                return (name, false);
+            }
+            DagNode::BinaryOp(op, left, right) => (left, right, op),
+            DagNode::UnaryOp(op, _) => unreachable!("Unknown operator {}", op),
+        };
+
+        // Plain Tseitin always adds both directions
+        let polarity: Polarity = if self.polarity_aware {
+            polarity
+        } else {
+            Polarity::Both
+        };
+        let (positive_done, negative_done) = self.encoded.get(&id).copied().unwrap_or_default();
+        let positive: bool = polarity.positive() && !positive_done;
+        let negative: bool = polarity.negative() && !negative_done;
+        if let Some(literal) = self.literals.get(&id) {
+            if !positive && !negative {
+                return literal.clone();
+            }
+        }
+        self.encoded
+            .insert(id, (positive_done || positive, negative_done || negative));
+
+        // Polarity the operands need for the directions added now
+        let wanted: Polarity = Polarity::from_directions(positive, negative);
+        let (left_polarity, right_polarity) = match op {
+            '&' | '|' => (wanted, wanted),
+            '>' => (wanted.flip(), wanted),
+            _ => (Polarity::Both, Polarity::Both),
+        };
+        let a: Literal = self.encode(evaluator, left, left_polarity);
+        let b: Literal = self.encode(evaluator, right, right_polarity);
+
+        let x: Literal = match self.literals.get(&id) {
+            Some(literal) => literal.clone(),
+            None => {
+                let name: String = self.fresh();
+                let operand = |(name, negated): &Literal| {
+                    let var: ExprNode<bool> = ExprNode::Var(name.clone());
+                    if *negated {
+                        ExprNode::UnaryOp('!', Box::new(var))
+                    } else {
+                        var
+                    }
+                };
+                self.definitions.push((
+                    name.clone(),
+                    ExprNode::BinaryOp(op, Box::new(operand(&a)), Box::new(operand(&b))),
+                ));
+                self.literals.insert(id, (name.clone(), false));
+                (name, false)
+            }
+        };
+
+        let (not_x, not_a, not_b) = (not(&x), not(&a), not(&b));
+        // x => gate
+        let positive_clauses: Vec<Vec<Literal>> = match op {
+            '&' => vec![
+                vec![not_x.clone(), a.clone()],
+                vec![not_x.clone(), b.clone()],
+            ],
+            '|' => vec![vec![not_x.clone(), a.clone(), b.clone()]],
+            '>' => vec![vec![not_x.clone(), not_a.clone(), b.clone()]],
+            '^' => vec![
+                vec![not_x.clone(), a.clone(), b.clone()],
+                vec![not_x.clone(), not_a.clone(), not_b.clone()],
+            ],
+            '=' => vec![
+                vec![not_x.clone(), not_a.clone(), b.clone()],
+                vec![not_x.clone(), a.clone(), not_b.clone()],
+            ],
+            _ => unreachable!("Unknown operator {}", op),
+        };
+        // gate => x
+        let negative_clauses: Vec<Vec<Literal>> = match op {
+            '&' => vec![vec![x.clone(), not_a, not_b]],
+            '|' => vec![vec![x.clone(), not_a], vec![x.clone(), not_b]],
+            '>' => vec![vec![x.clone(), a], vec![x.clone(), not_b]],
+            '^' => vec![
+                vec![x.clone(), not_a.clone(), b.clone()],
+                vec![x.clone(), a.clone(), not_b.clone()],
+            ],
+            _ => vec![vec![x.clone(), a, b], vec![x.clone(), not_a, not_b]],
+        };
+        if positive {
+            self.clauses.extend(positive_clauses);
+        }
+        if negative {
+            self.clauses.extend(negative_clauses);
+        }
+        x
+    }
+}
+
+impl ExpressionEvaluator<bool, BooleanOperations> {
+    /*
+        Tseitin transformation: CNF with a size linear in the formula, over the variables
+        of the formula and auxiliary ones named _t0, _t1, ...
+        With polarity_aware, the Plaisted-Greenbaum variant only adds the implications
+        each subformula needs for the polarity it appears with, about half the clauses.
+    */
+    pub fn tseitin_transformation(
+        &self,
+        formula: &str,
+        polarity_aware: bool,
+    ) -> Result<TseitinCnf, ParseError> {
+        Ok(self.tseitin_encode(&self.parse(formula)?, polarity_aware))
+    }
+
+    pub fn tseitin_encode(&self, node: &ExprNode<bool>, polarity_aware: bool) -> TseitinCnf {
+        let mut arena: ExprArena<bool> = ExprArena::new();
+        let root: NodeId = arena.insert_expr(node);
+        let mut variables: Vec<String> = Vec::new();
+        self.collect_variables(node, &mut variables);
+        variables.sort();
+        variables.dedup();
+
+        let mut encoder = TseitinEncoder {
+            arena,
+            polarity_aware,
+            taken: variables.iter().cloned().collect(),
+            next: 0,
+            literals: HashMap::new(),
+            encoded: HashMap::new(),
+            clauses: Vec::new(),
+            definitions: Vec::new(),
+        };
+        let literal: Literal = encoder.encode(self, root, Polarity::Positive);
+        encoder.clauses.push(vec![literal]);
+
+        TseitinCnf {
+            clauses: encoder.clauses,
+            variables,
+            definitions: encoder.definitions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::truth_table::generate_truth_table;
+
+    // Models of the formula, as the sorted values of its variables
+    fn models(
+        evaluator: &mut ExpressionEvaluator<bool, BooleanOperations>,
+        formula: &str,
+    ) -> HashSet<Vec<(String, bool)>> {
+        let table = generate_truth_table(formula, evaluator).unwrap();
+        table
+            .rows()
+            .filter(|(_, result)| *result)
+            .map(|(assignment, _)| table.variables.iter().cloned().zip(assignment).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_equisatisfiable() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        let formulas = [
+            "AB&!",
+            "AB|C&!",
+            "AB^C=",
+            "AB>C>D|!",
+            "AB=!C^",
+            "AB&AB&|C>",
+            "A1&B0|^",
+            "AB↑C↓",
+            "AA!&",
+        ];
+        for formula in formulas {
+            let expected = models(&mut evaluator, formula);
+            for polarity_aware in [false, true] {
+                let cnf: TseitinCnf = evaluator
+                    .tseitin_transformation(formula, polarity_aware)
+                    .unwrap();
+                let rpn: String = evaluator.to_rpn(&cnf.to_expr());
+                let table = generate_truth_table(&rpn, &mut evaluator).unwrap();
+
+                // Every model of the CNF is a model of the formula once projected, and back
+                let projected: HashSet<Vec<(String, bool)>> = table
+                    .rows()
+                    .filter(|(_, result)| *result)
+                    .map(|(assignment, _)| {
+                        let model: HashMap<String, bool> =
+                            table.variables.iter().cloned().zip(assignment).collect();
+                        let mut values: Vec<(String, bool)> =
+                            cnf.project(&model).into_iter().collect();
+                        values.sort();
+                        values
+                    })
+                    .collect();
+                assert_eq!(projected, expected, "{} => {}", formula, rpn);
+            }
+        }
+    }
+
+    #[test]
+    fn test_definitions() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // _t0 is a taken name, so the auxiliary variables start at _t1
+        let cnf = evaluator.tseitin_transformation("_t0 B&C|", false).unwrap();
+        assert_eq!(cnf.variables, vec!["B", "C", "_t0"]);
+        assert_eq!(cnf.definitions.len(), 2);
+        assert_eq!(cnf.to_cnf().variables(), ["B", "C", "_t0", "_t1", "_t2"]);
+        assert!(cnf.is_auxiliary("_t1") && !cnf.is_auxiliary("_t0"));
+        assert_eq!(
+            evaluator.to_rpn(&cnf.subformula("_t2").unwrap()),
+            "_t0 B&C|"
+        );
+        // A & B, both directions, then (_t2 | !_t1 | C) ... and the root as a unit clause
+        assert_eq!(cnf.clauses.len(), 3 + 3 + 1);
+        assert_eq!(
+            cnf.clauses.last().unwrap(),
+            &vec![("_t2".to_string(), false)]
+        );
+
+        let cnf = evaluator.tseitin_transformation("_t0 B&C|", true).unwrap();
+        assert_eq!(cnf.clauses.len(), 2 + 1 + 1);
+    }
+
+    #[test]
+    fn test_linear_size() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // Distributing this one gives 2^39 clauses
+        let names: Vec<String> = (0..40).map(|i| format!("x{}", i)).collect();
+        let formula: String = evaluator.infix_to_rpn(&names.join(" = ")).unwrap();
+        let cnf = evaluator.tseitin_transformation(&formula, false).unwrap();
+        assert_eq!(cnf.definitions.len(), 39);
+        assert_eq!(cnf.clauses.len(), 4 * 39 + 1);
+        assert_eq!(cnf.variables.len(), 40);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::aux::check_only_vars;
+use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
+use crate::aux::lexer::{join_rpn, rpn_operand};
+use crate::aux::ParseError;
+use crate::boolean_operations::BooleanOperations;
+use crate::truth_table::generate_truth_table;
+
+impl ExpressionEvaluator<bool, BooleanOperations> {
+    pub fn disjunctive_normal_form(
+        &mut self,
+        formula: &str,
+        truth_table: Option<bool>,
+    ) -> Result<String, ParseError> {
+        // The truth table method unless asked otherwise, like conjunctive_normal_form
+        if truth_table.unwrap_or(true) {
+            self.derive_dnf_from_truth_table(formula)
+        } else {
+            // Constants can be mixed with variables, they are folded away
+            let tree: ExprNode<bool> = self.parse(formula)?;
+
+            // Convert the tree to NNF then distribute & over | to form dnf
+            let nnf_tree = self.to_nnf(tree);
+            let dnf_tree = self.to_dnf(nnf_tree);
+
+            Ok(self.to_rpn(&dnf_tree))
+        }
+    }
+
+    pub fn derive_dnf_from_truth_table(&mut self, formula: &str) -> Result<String, ParseError> {
+        // Generate the truth table
+        let truth_table = generate_truth_table(formula, self)?;
+
+        // Collect minterms for rows where the formula evaluates to true
+        let mut minterms: Vec<String> = Vec::new();
+
+        for (assignment, result) in truth_table.rows() {
+            if result {
+                // Create a minterm for this row
+                let mut minterm: Vec<String> = Vec::new();
+
+                for (var, value) in truth_table.variables.iter().zip(assignment) {
+                    if value {
+                        // If the variable is true, add the variable itself
+                        minterm.push(rpn_operand(var));
+                    } else {
+                        // If the variable is false, add its negation
+                        minterm.push(format!("{}!", rpn_operand(var)));
+                    }
+                }
+
+                // Without variables the only row is an empty minterm, the formula is always true
+                if minterm.is_empty() {
+                    return Ok("1".to_string());
+                }
+
+                // Combine the literals in the minterm with the correct number of & operators
+                let mut rpn_minterm = minterm
+                    .iter()
+                    .fold(String::new(), |rpn, literal| join_rpn(&rpn, literal));
+                for _ in 1..minterm.len() {
+                    rpn_minterm.push('&');
+                }
+
+                minterms.push(rpn_minterm);
+            }
+        }
+        /*
+            A contradiction has no minterm, its DNF is the constant 0
+            https://en.wikipedia.org/wiki/Contradiction
+        */
+        if minterms.is_empty() {
+            return Ok("0".to_string());
+        }
+        // Combine all minterms with OR
+        let mut dnf = minterms
+            .iter()
+            .fold(String::new(), |rpn, minterm| join_rpn(&rpn, minterm));
+        for _ in 1..minterms.len() {
+            dnf.push('|');
+        }
+
+        Ok(dnf.trim_end().to_string())
+    }
+
+    /*
+        Expects a formula in NNF, so & and | are the only binary operators left.
+        Constants are folded away as in the clauses of the CNF: a constant is only left
+        when the whole formula is one.
+    */
+    fn to_dnf(&self, node: ExprNode<bool>) -> ExprNode<bool> {
+        match node {
+            ExprNode::BinaryOp('|', left, right) => {
+                let left_dnf = self.to_dnf(*left);
+                let right_dnf = self.to_dnf(*right);
+                self.disjunction(left_dnf, right_dnf)
+            }
+            ExprNode::BinaryOp('&', left, right) => {
+                let left_dnf = self.to_dnf(*left);
+                let right_dnf = self.to_dnf(*right);
+                self.distribute_conjunction(left_dnf, right_dnf)
+            }
+            ExprNode::UnaryOp('!', child) => match *child {
+                ExprNode::Const(value) => ExprNode::Const(!value),
+                child => ExprNode::UnaryOp('!', Box::new(child)),
+            },
+            // Literals: constants and variables
+            node => node,
+        }
+    }
+
+    // left | right, where 1 absorbs the other operand and 0 is dropped
+    fn disjunction(&self, left: ExprNode<bool>, right: ExprNode<bool>) -> ExprNode<bool> {
+        match (left, right) {
+            (ExprNode::Const(true), _) | (_, ExprNode::Const(true)) => ExprNode::Const(true),
+            (ExprNode::Const(false), other) | (other, ExprNode::Const(false)) => other,
+            (left, right) => ExprNode::BinaryOp('|', Box::new(left), Box::new(right)),
+        }
+    }
+
+    // (A | B) & C => (A & C) | (B & C), for operands already in DNF
+    fn distribute_conjunction(
+        &self,
+        left: ExprNode<bool>,
+        right: ExprNode<bool>,
+    ) -> ExprNode<bool> {
+        match (left, right) {
+            (ExprNode::Const(false), _) | (_, ExprNode::Const(false)) => ExprNode::Const(false),
+            (ExprNode::Const(true), other) | (other, ExprNode::Const(true)) => other,
+            (ExprNode::BinaryOp('|', first, second), right) => {
+                let first_dnf = self.distribute_conjunction(*first, right.clone());
+                let second_dnf = self.distribute_conjunction(*second, right);
+                self.disjunction(first_dnf, second_dnf)
+            }
+            (left, ExprNode::BinaryOp('|', first, second)) => {
+                let first_dnf = self.distribute_conjunction(left.clone(), *first);
+                let second_dnf = self.distribute_conjunction(left, *second);
+                self.disjunction(first_dnf, second_dnf)
+            }
+            (left, right) => ExprNode::BinaryOp('&', Box::new(left), Box::new(right)),
+        }
+    }
+}
+
+pub fn run_disjunctive_normal_form() {
+    let mut boolean_evaluation: ExpressionEvaluator<bool, BooleanOperations> =
+        ExpressionEvaluator::<bool, BooleanOperations>::new();
+    println!("\n\tRunning disjunctive_normal_form function\n");
+    let formula = "AB|C&";
+    println!("Original formula: {}", formula);
+    match boolean_evaluation.disjunctive_normal_form(formula, Some(false)) {
+        Ok(dnf) => {
+            println!("Disjunctive Normal Form {}", dnf);
+            println!(
+                "Formula {}",
+                boolean_evaluation.print_formula(
+                    &boolean_evaluation
+                        .build_tree(&dnf, check_only_vars(&dnf), None)
+                        .unwrap()
+                )
+            );
+        }
+        Err(err) => println!("Disjunctive Normal Form: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aux::Assignment;
+
+    #[test]
+    fn test_tree() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        assert_eq!(
+            evaluator.disjunctive_normal_form("A", Some(false)).unwrap(),
+            "A"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB|C&", Some(false))
+                .unwrap(),
+            "AC&BC&|"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB|CD|&", Some(false))
+                .unwrap(),
+            "AC&AD&|BC&BD&||"
+        );
+        // De Morgan first: !(A | B) & C is !A & !B & C
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB|!C&", Some(false))
+                .unwrap(),
+            "A!B!&C&"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB>", Some(false))
+                .unwrap(),
+            "A!B|"
+        );
+        // Constants are folded into the terms
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("A1&", Some(false))
+                .unwrap(),
+            "A"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("A0|B!1&&C|", Some(false))
+                .unwrap(),
+            "AB!&C|"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB|0&", Some(false))
+                .unwrap(),
+            "0"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("A0!|", Some(false))
+                .unwrap(),
+            "1"
+        );
+    }
+
+    #[test]
+    fn test_truth_table_method() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        assert_eq!(
+            evaluator.disjunctive_normal_form("A", Some(true)).unwrap(),
+            "A"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AA&AA&&", Some(true))
+                .unwrap(),
+            "A"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB|", Some(true))
+                .unwrap(),
+            "A!B&AB!&AB&||"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AB|C&", Some(true))
+                .unwrap(),
+            "A!BC&&AB!C&&ABC&&||"
+        );
+        // A contradiction has no minterm and is 0
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("AA!&", Some(true))
+                .unwrap(),
+            "0"
+        );
+        assert_eq!(
+            evaluator
+                .disjunctive_normal_form("11&", Some(true))
+                .unwrap(),
+            "1"
+        );
+        assert_eq!(
+            evaluator.disjunctive_normal_form("AB|", None).unwrap(),
+            "A!B&AB!&AB&||"
+        );
+    }
+
+    // Every term is an AND of literals and the formula is an OR of terms
+    fn is_dnf(node: &ExprNode<bool>, inside_term: bool) -> bool {
+        match node {
+            ExprNode::Var(_) | ExprNode::Const(_) => true,
+            ExprNode::UnaryOp('!', child) => matches!(child.as_ref(), ExprNode::Var(_)),
+            ExprNode::BinaryOp('&', left, right) => is_dnf(left, true) && is_dnf(right, true),
+            ExprNode::BinaryOp('|', left, right) => {
+                !inside_term && is_dnf(left, false) && is_dnf(right, false)
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_result_truth_table() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formulas = [
+            "AB&!",
+            "AB|!C!&",
+            "AB^C=",
+            "AB>C>D|",
+            "AB|C&!D^",
+            "reset_n x12|!A&",
+            "A1^B0=|",
+            "11&",
+            "AB^AB=&",
+        ];
+        for formula in formulas {
+            let table = generate_truth_table(formula, &mut evaluator).unwrap();
+            for truth_table in [true, false] {
+                let dnf = evaluator
+                    .disjunctive_normal_form(formula, Some(truth_table))
+                    .unwrap();
+                let tree = evaluator.parse(&dnf).unwrap();
+                assert!(is_dnf(&tree, false), "{} => {}", formula, dnf);
+                // 0 and 1 have no variables, so compare with the variables of the formula
+                for (assignment, result) in table.rows() {
+                    let values: Assignment<bool> =
+                        table.variables.iter().cloned().zip(assignment).collect();
+                    assert_eq!(
+                        evaluator.evaluate_assignment(&tree, &values).unwrap(),
+                        result,
+                        "{} => {}",
+                        formula,
+                        dnf
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod dnf;
+
+pub use dnf::run_disjunctive_normal_form;
\ No newline at end of file
@@ -1,8 +1,10 @@
 pub mod boolean_evaluation;
 pub mod cnf;
+pub mod dnf;
 pub mod nnf;
 pub mod sat;
 
 pub use boolean_evaluation::{run_boolean_operations, BooleanOperations};
 pub use cnf::run_conjunctive_normal_form;
+pub use dnf::run_disjunctive_normal_form;
 pub use nnf::run_negation_normal_form;
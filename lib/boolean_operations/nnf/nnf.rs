@@ -1,8 +1,11 @@
 use crate::aux::check_only_vars;
+use crate::aux::expr_arena::{DagNode, ExprArena, NodeId};
 use crate::aux::expresion_eval::LogicValue;
 use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
 use crate::aux::traits::Algebra;
 use crate::boolean_operations::BooleanOperations;
+use std::collections::HashMap;
+use std::hash::Hash;
 
 impl<T, O: Algebra<T>> ExpressionEvaluator<T, O>
 where
@@ -20,31 +23,29 @@ where
         self.to_rpn(&nnf_tree)
     }
 
-    pub(in crate::boolean_operations) fn to_nnf(
-        &self,
-        node: ExprNode<T>,
-    ) -> ExprNode<T>
+    pub(in crate::boolean_operations) fn to_nnf(&self, node: ExprNode<T>) -> ExprNode<T>
     where
         T: From<LogicValue> + std::fmt::Display + Clone,
     {
-        match node {
+        // Registered connectives are first written with the core ones
+        match self.rewrite_connective(node) {
             // Handle double negation
-            ExprNode::UnaryOp('!', child) => match *child {
+            ExprNode::UnaryOp('!', child) => match self.rewrite_connective(*child) {
                 ExprNode::UnaryOp('!', child2) => self.to_nnf(*child2), // !!A => A
-                ExprNode::BinaryOp(op, left, right) => {
+                ExprNode::BinaryOp(op @ ('&' | '|'), left, right) => {
                     // De Morgan's laws: !(A & B) => !A | !B, !(A | B) => !A & !B
-                    let new_op = match op {
-                        '&' => '|',
-                        '|' => '&',
-                        _ => op,
-                    };
+                    let new_op = if op == '&' { '|' } else { '&' };
                     ExprNode::BinaryOp(
                         new_op,
                         Box::new(self.to_nnf(ExprNode::UnaryOp('!', left))),
                         Box::new(self.to_nnf(ExprNode::UnaryOp('!', right))),
                     )
                 }
-                _ => ExprNode::UnaryOp('!', Box::new(self.to_nnf(*child))),
+                // Rewrite ^, > and = into & and | first, then push the negation inside
+                ExprNode::BinaryOp(op, left, right) => {
+                    self.negate(self.to_nnf(ExprNode::BinaryOp(op, left, right)))
+                }
+                child => ExprNode::UnaryOp('!', Box::new(self.to_nnf(child))),
             },
             // Handle binary operators
             ExprNode::BinaryOp(op, left, right) => {
@@ -53,36 +54,35 @@ where
                 match op {
                     '&' => ExprNode::BinaryOp('&', Box::new(left_nnf), Box::new(right_nnf)),
                     '|' => ExprNode::BinaryOp('|', Box::new(left_nnf), Box::new(right_nnf)),
-                    'ˆ' => {
-                        // A ⊕ B => (A | B) & !(A & B)
+                    '^' => {
+                        // A ⊕ B => (A | B) & (!A | !B)
                         let or_expr = ExprNode::BinaryOp(
                             '|',
                             Box::new(left_nnf.clone()),
                             Box::new(right_nnf.clone()),
                         );
-                        let and_expr =
-                            ExprNode::BinaryOp('&', Box::new(left_nnf), Box::new(right_nnf));
-                        ExprNode::BinaryOp(
-                            '&',
-                            Box::new(or_expr),
-                            Box::new(ExprNode::UnaryOp('!', Box::new(and_expr))),
-                        )
+                        let nand_expr = ExprNode::BinaryOp(
+                            '|',
+                            Box::new(self.negate(left_nnf)),
+                            Box::new(self.negate(right_nnf)),
+                        );
+                        ExprNode::BinaryOp('&', Box::new(or_expr), Box::new(nand_expr))
                     }
                     '>' => {
                         // A ⇒ B => !A | B
-                        let not_left = ExprNode::UnaryOp('!', Box::new(left_nnf));
+                        let not_left = self.negate(left_nnf);
                         ExprNode::BinaryOp('|', Box::new(not_left), Box::new(right_nnf))
                     }
                     '=' => {
                         // A ⇔ B => (A ⇒ B) & (B ⇒ A)
                         let left_to_right = ExprNode::BinaryOp(
                             '|',
-                            Box::new(ExprNode::UnaryOp('!', Box::new(left_nnf.clone()))),
+                            Box::new(self.negate(left_nnf.clone())),
                             Box::new(right_nnf.clone()),
                         );
                         let right_to_left = ExprNode::BinaryOp(
                             '|',
-                            Box::new(ExprNode::UnaryOp('!', Box::new(right_nnf))),
+                            Box::new(self.negate(right_nnf)),
                             Box::new(left_nnf),
                         );
                         ExprNode::BinaryOp('&', Box::new(left_to_right), Box::new(right_to_left))
@@ -91,9 +91,115 @@ where
                 }
             }
             // Handle constants and variables
-            _ => node,
+            node => node,
+        }
+    }
+
+    /*
+        NNF of a formula stored in an arena. Every node is converted at most once per polarity,
+        so the result stays linear in the size of the DAG even for chains of = and ^,
+        which double in size at every level when converted as a tree.
+    */
+    pub fn to_nnf_dag(&self, arena: &mut ExprArena<T>, root: NodeId) -> NodeId
+    where
+        T: Eq + Hash,
+    {
+        let mut memo: HashMap<(NodeId, bool), NodeId> = HashMap::new();
+        self.nnf_dag(arena, root, false, &mut memo)
+    }
+
+    // NNF of the node, or of its negation when negated is true
+    fn nnf_dag(
+        &self,
+        arena: &mut ExprArena<T>,
+        id: NodeId,
+        negated: bool,
+        memo: &mut HashMap<(NodeId, bool), NodeId>,
+    ) -> NodeId
+    where
+        T: Eq + Hash,
+    {
+        if let Some(&done) = memo.get(&(id, negated)) {
+            return done;
+        }
+        // Registered connectives are first written with the core ones
+        let rewritten: NodeId = self.rewrite_connective_dag(arena, id);
+        let node: DagNode<T> = arena.node(rewritten).clone();
+        let result: NodeId = match node {
+            DagNode::UnaryOp('!', child) => self.nnf_dag(arena, child, !negated, memo),
+            // De Morgan's laws: !(A & B) => !A | !B, !(A | B) => !A & !B
+            DagNode::BinaryOp(op @ ('&' | '|'), left, right) => {
+                let new_op: char = match (op, negated) {
+                    ('&', true) => '|',
+                    ('|', true) => '&',
+                    _ => op,
+                };
+                let left: NodeId = self.nnf_dag(arena, left, negated, memo);
+                let right: NodeId = self.nnf_dag(arena, right, negated, memo);
+                arena.binary(new_op, left, right)
+            }
+            // A ⇒ B => !A | B, !(A ⇒ B) => A & !B
+            DagNode::BinaryOp('>', left, right) => {
+                let left: NodeId = self.nnf_dag(arena, left, !negated, memo);
+                let right: NodeId = self.nnf_dag(arena, right, negated, memo);
+                arena.binary(if negated { '&' } else { '|' }, left, right)
+            }
+            DagNode::BinaryOp(op @ ('^' | '='), left, right) => {
+                let pos_left: NodeId = self.nnf_dag(arena, left, false, memo);
+                let neg_left: NodeId = self.nnf_dag(arena, left, true, memo);
+                let pos_right: NodeId = self.nnf_dag(arena, right, false, memo);
+                let neg_right: NodeId = self.nnf_dag(arena, right, true, memo);
+                let (outer, inner, first, second) = match (op, negated) {
+                    // A ⊕ B => (A | B) & (!A | !B), !(A ⊕ B) => (!A & !B) | (A & B)
+                    ('^', false) => ('&', '|', (pos_left, pos_right), (neg_left, neg_right)),
+                    ('^', true) => ('|', '&', (neg_left, neg_right), (pos_left, pos_right)),
+                    // A ⇔ B => (!A | B) & (!B | A), !(A ⇔ B) => (A & !B) | (B & !A)
+                    (_, false) => ('&', '|', (neg_left, pos_right), (neg_right, pos_left)),
+                    (_, true) => ('|', '&', (pos_left, neg_right), (pos_right, neg_left)),
+                };
+                let first: NodeId = arena.binary(inner, first.0, first.1);
+                let second: NodeId = arena.binary(inner, second.0, second.1);
+                arena.binary(outer, first, second)
+            }
+            // Constants, variables and unknown operators only get the negation in front
+            DagNode::UnaryOp(op, child) => {
+                let child: NodeId = self.nnf_dag(arena, child, false, memo);
+                let id: NodeId = arena.unary(op, child);
+                self.negate_dag(arena, id, negated)
+            }
+            DagNode::BinaryOp(op, left, right) => {
+                let left: NodeId = self.nnf_dag(arena, left, false, memo);
+                let right: NodeId = self.nnf_dag(arena, right, false, memo);
+                let id: NodeId = arena.binary(op, left, right);
+                self.negate_dag(arena, id, negated)
+            }
+            leaf => {
+                let id: NodeId = arena.intern(leaf);
+                self.negate_dag(arena, id, negated)
+            }
+        };
+        memo.insert((id, negated), result);
+        result
+    }
+
+    fn negate_dag(&self, arena: &mut ExprArena<T>, id: NodeId, negated: bool) -> NodeId
+    where
+        T: Eq + Hash,
+    {
+        if negated {
+            arena.unary('!', id)
+        } else {
+            id
         }
     }
+
+    // Negation of a formula already in NNF, kept in NNF
+    fn negate(&self, node: ExprNode<T>) -> ExprNode<T>
+    where
+        T: From<LogicValue> + std::fmt::Display + Clone,
+    {
+        self.to_nnf(ExprNode::UnaryOp('!', Box::new(node)))
+    }
 }
 
 pub fn run_negation_normal_form() {
@@ -156,4 +262,27 @@ mod tests {
             generate_truth_table(&nnf, &mut evaluator).unwrap()
         );
     }
+    #[test]
+    fn test_exclusive_disjunction() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // Both carets are the same operator
+        assert_eq!(evaluator.negation_normal_form("AB^"), "AB|A!B!|&");
+        assert_eq!(evaluator.negation_normal_form("ABˆ"), "AB|A!B!|&");
+        assert_eq!(evaluator.negation_normal_form("AB^!"), "A!B!&AB&|");
+
+        // Negated ^, > and = are rewritten before the negation is pushed inside
+        for formula in ["AB^!", "AB>!", "AB=!", "AB&C>", "AB^C=!"] {
+            let nnf = evaluator.negation_normal_form(formula);
+            assert!(
+                !nnf.contains(|c| "^>=".contains(c)) && !nnf.contains("&!") && !nnf.contains("|!"),
+                "{} is not in NNF",
+                nnf
+            );
+            assert_eq!(
+                generate_truth_table(formula, &mut evaluator).unwrap(),
+                generate_truth_table(&nnf, &mut evaluator).unwrap()
+            );
+        }
+    }
 }
@@ -0,0 +1,206 @@
+use crate::aux::{Assignment, ExpressionEvaluator, ParseError};
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal, TseitinCnf};
+use crate::boolean_operations::sat::cdcl::CdclSolver;
+use crate::boolean_operations::sat::sat::SatResult;
+use crate::boolean_operations::BooleanOperations;
+use std::iter::FusedIterator;
+
+/*
+    Lazy enumeration of the models of a Cnf, projected onto some of its variables.
+    Every model found is blocked with a clause over the projected variables only,
+    so each projection comes out once and the solver keeps what it learned in between.
+    Nothing is solved before next is called, so a caller can stop at any point.
+*/
+#[derive(Debug, Clone)]
+pub struct Models {
+    solver: CdclSolver,
+    projection: Vec<usize>,
+    done: bool,
+}
+
+impl Models {
+    // The projected variables, in the order of the values of every model
+    pub fn projection(&self) -> Vec<&str> {
+        self.projection
+            .iter()
+            .map(|&var| self.solver.variables()[var].as_str())
+            .collect()
+    }
+}
+
+impl Iterator for Models {
+    type Item = Assignment<bool>;
+
+    fn next(&mut self) -> Option<Assignment<bool>> {
+        if self.done {
+            return None;
+        }
+        let model: Assignment<bool> = match self.solver.solve() {
+            SatResult::Sat(model) => model,
+            SatResult::Unsat | SatResult::Unknown => {
+                self.done = true;
+                return None;
+            }
+        };
+        let values: &Vec<bool> = model.values();
+        let projected: Assignment<bool> = self
+            .projection
+            .iter()
+            .map(|&var| (self.solver.variables()[var].clone(), values[var]))
+            .collect();
+
+        // The same values on the projection can't come back
+        let blocking: Clause = self
+            .projection
+            .iter()
+            .map(|&var| Literal::new(var, values[var]))
+            .collect();
+        if blocking.is_empty() {
+            self.done = true;
+        } else {
+            self.solver.add_clause(&blocking);
+        }
+        Some(projected)
+    }
+}
+
+impl FusedIterator for Models {}
+
+impl Cnf {
+    pub fn models(&self) -> Models {
+        self.projected_models(&(0..self.num_variables()).collect::<Vec<usize>>())
+    }
+
+    // Each model only has the values of the variables in projection, in that order
+    pub fn projected_models(&self, projection: &[usize]) -> Models {
+        Models {
+            solver: CdclSolver::new(self),
+            projection: projection.to_vec(),
+            done: false,
+        }
+    }
+}
+
+impl ExpressionEvaluator<bool, BooleanOperations> {
+    /*
+        Every satisfying assignment of a formula, projected onto the given variables or
+        onto all of them, in sorted order, so the values of a model are a row of its truth table.
+        A variable of the projection that isn't in the formula is an error.
+    */
+    pub fn all_sat(
+        &self,
+        formula: &str,
+        projection: Option<&[&str]>,
+    ) -> Result<Models, ParseError> {
+        let encoding: TseitinCnf = self.tseitin_transformation(formula, true)?;
+        let cnf: Cnf = encoding.to_cnf();
+        let projection: Vec<usize> = match projection {
+            None => (0..encoding.variables.len()).collect(),
+            Some(names) => names
+                .iter()
+                .map(
+                    |&name| match encoding.variables.iter().position(|var| var == name) {
+                        Some(var) => Ok(var),
+                        None => Err(ParseError::UnusedVariable {
+                            name: name.to_string(),
+                        }),
+                    },
+                )
+                .collect::<Result<Vec<usize>, ParseError>>()?,
+        };
+        Ok(cnf.projected_models(&projection))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::truth_table::generate_truth_table;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_matches_truth_table() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        let formulas = [
+            "AB|",
+            "AB&!",
+            "AB^C=",
+            "AB>C>D|",
+            "AA!&",
+            "AB↑C↓",
+            "A1|",
+            "reset_n x12|!A&",
+        ];
+        for formula in formulas {
+            let table = generate_truth_table(formula, &mut evaluator).unwrap();
+            let expected: HashSet<Vec<bool>> = table
+                .rows()
+                .filter(|(_, result)| *result)
+                .map(|(assignment, _)| assignment)
+                .collect();
+            let models: Vec<Vec<bool>> = evaluator
+                .all_sat(formula, None)
+                .unwrap()
+                .map(|model| model.values().clone())
+                .collect();
+            assert_eq!(models.len(), expected.len(), "{}", formula);
+            assert_eq!(
+                models.into_iter().collect::<HashSet<Vec<bool>>>(),
+                expected,
+                "{}",
+                formula
+            );
+        }
+    }
+
+    #[test]
+    fn test_projection() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // (A | B) & C has three models, but only two values of A
+        let models: Models = evaluator.all_sat("AB|C&", Some(&["A"])).unwrap();
+        assert_eq!(models.projection(), ["A"]);
+        let mut values: Vec<bool> = models.map(|model| model.values()[0]).collect();
+        values.sort();
+        assert_eq!(values, [false, true]);
+
+        let models: Vec<Assignment<bool>> = evaluator
+            .all_sat("AB|C&", Some(&["C", "B"]))
+            .unwrap()
+            .collect();
+        assert_eq!(models.len(), 2);
+        assert!(models
+            .iter()
+            .all(|model| model.iter().next() == Some(("C", &true))));
+
+        // An empty projection only says if there is a model
+        assert_eq!(evaluator.all_sat("AB|", Some(&[])).unwrap().count(), 1);
+        assert_eq!(evaluator.all_sat("AA!&", Some(&[])).unwrap().count(), 0);
+        assert_eq!(
+            evaluator.all_sat("AB|", Some(&["C"])).unwrap_err(),
+            ParseError::UnusedVariable {
+                name: "C".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_lazy() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // 2^40 - 1 models, far too many to list
+        let names: Vec<String> = (0..40).map(|i| format!("x{}", i)).collect();
+        let formula: String = evaluator.infix_to_rpn(&names.join(" | ")).unwrap();
+        let models: Vec<Assignment<bool>> =
+            evaluator.all_sat(&formula, None).unwrap().take(5).collect();
+        assert_eq!(models.len(), 5);
+        assert!(models.iter().all(|model| model.values().contains(&true)));
+
+        let cnf: Cnf = Cnf::from_rpn("AB|B!C|&").unwrap();
+        let mut models: Models = cnf.models();
+        assert_eq!(models.by_ref().count(), 4);
+        assert_eq!(models.next(), None);
+    }
+}
@@ -0,0 +1,890 @@
+use crate::aux::Assignment;
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal};
+use crate::boolean_operations::sat::drat::DratProof;
+use crate::boolean_operations::sat::sat::{SatResult, Solver};
+
+// Multiplier of the activity bump after every conflict, for variables and clauses
+const VAR_DECAY: f64 = 0.95;
+const CLAUSE_DECAY: f64 = 0.999;
+// Conflicts before the first restart, the next ones follow the Luby sequence
+const RESTART_BASE: u64 = 100;
+// Growth of the number of learned clauses kept after every reduction
+const LEARNED_GROWTH: f64 = 1.1;
+
+// Counters of the work done by a CdclSolver since it was created
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SolverStats {
+    pub conflicts: u64,
+    pub decisions: u64,
+    pub propagations: u64,
+    pub restarts: u64,
+    pub learned: u64,
+    pub deleted: u64,
+}
+
+#[derive(Debug, Clone)]
+struct StoredClause {
+    // The first two literals are the watched ones, the first is the implied one of a reason
+    literals: Vec<Literal>,
+    learned: bool,
+    activity: f64,
+    deleted: bool,
+}
+
+/*
+    Binary max-heap of variables by activity, with the position of each variable
+    so a bumped variable can move up without searching for it.
+*/
+#[derive(Debug, Clone, Default)]
+struct VarOrder {
+    heap: Vec<usize>,
+    position: Vec<Option<usize>>,
+}
+
+impl VarOrder {
+    fn new(num_variables: usize) -> Self {
+        VarOrder {
+            heap: Vec::with_capacity(num_variables),
+            position: vec![None; num_variables],
+        }
+    }
+
+    fn contains(&self, var: usize) -> bool {
+        self.position[var].is_some()
+    }
+
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            return;
+        }
+        self.position[var] = Some(self.heap.len());
+        self.heap.push(var);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+
+    // Restores the order after the activity of var went up
+    fn increase(&mut self, var: usize, activity: &[f64]) {
+        if let Some(index) = self.position[var] {
+            self.sift_up(index, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let var: usize = *self.heap.first()?;
+        let last: usize = self.heap.pop().unwrap();
+        self.position[var] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.position[last] = Some(0);
+            self.sift_down(0, activity);
+        }
+        Some(var)
+    }
+
+    fn sift_up(&mut self, mut index: usize, activity: &[f64]) {
+        let var: usize = self.heap[index];
+        while index > 0 {
+            let parent: usize = (index - 1) / 2;
+            if activity[self.heap[parent]] >= activity[var] {
+                break;
+            }
+            self.heap[index] = self.heap[parent];
+            self.position[self.heap[index]] = Some(index);
+            index = parent;
+        }
+        self.heap[index] = var;
+        self.position[var] = Some(index);
+    }
+
+    fn sift_down(&mut self, mut index: usize, activity: &[f64]) {
+        let var: usize = self.heap[index];
+        loop {
+            let left: usize = 2 * index + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right: usize = left + 1;
+            let child: usize = if right < self.heap.len()
+                && activity[self.heap[right]] > activity[self.heap[left]]
+            {
+                right
+            } else {
+                left
+            };
+            if activity[self.heap[child]] <= activity[var] {
+                break;
+            }
+            self.heap[index] = self.heap[child];
+            self.position[self.heap[index]] = Some(index);
+            index = child;
+        }
+        self.heap[index] = var;
+        self.position[var] = Some(index);
+    }
+}
+
+fn literal_value(assigns: &[Option<bool>], literal: Literal) -> Option<bool> {
+    assigns[literal.var()].map(|value| literal.value(value))
+}
+
+// 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ... for i = 0, 1, 2, ...
+fn luby(mut i: u64) -> u64 {
+    let mut size: u64 = 1;
+    let mut power: u64 = 1;
+    while size < i + 1 {
+        size = 2 * size + 1;
+        power *= 2;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        power /= 2;
+        i %= size;
+    }
+    power
+}
+
+/*
+    Conflict-driven clause learning solver.
+    Propagation watches two literals per clause, so only the clauses watching a literal
+    that just became false are visited. Every conflict is analysed up to its first unique
+    implication point, the learned clause is added and the search jumps back to the level
+    where it becomes unit. Decisions take the most active variable (VSIDS) with the value
+    it last had, the search restarts on a Luby schedule and the least active half of the
+    learned clauses is deleted when there are too many of them.
+*/
+#[derive(Debug, Clone)]
+pub struct CdclSolver {
+    variables: Vec<String>,
+    // Variables made by new_variable, they have no name and no place in the models
+    anonymous: Vec<bool>,
+    clauses: Vec<StoredClause>,
+    // Clauses watching each literal, by code
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    trail: Vec<Literal>,
+    // Start of each decision level in the trail
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    var_increment: f64,
+    clause_increment: f64,
+    order: VarOrder,
+    max_learned: f64,
+    // Learned clauses in clauses, kept up to date so search doesn't count them
+    num_learned: usize,
+    // False once a conflict without any decision was found
+    consistent: bool,
+    // Literals every decision level below their count starts with, for the current solve
+    assumptions: Vec<Literal>,
+    failed: Vec<Literal>,
+    stats: SolverStats,
+    // Learned and deleted clauses, when the solver was asked to keep them
+    proof: Option<DratProof>,
+}
+
+impl CdclSolver {
+    pub fn new(cnf: &Cnf) -> Self {
+        CdclSolver::build(cnf, None)
+    }
+
+    /*
+        Same solver, but every clause it learns or deletes goes into a DRAT proof.
+        Once solve says Unsat without assumptions, the proof ends with the empty clause
+        and Cnf::check_proof can verify it against the clauses the solver was given.
+    */
+    pub fn with_proof(cnf: &Cnf) -> Self {
+        CdclSolver::build(cnf, Some(DratProof::new()))
+    }
+
+    fn build(cnf: &Cnf, proof: Option<DratProof>) -> Self {
+        let num_variables: usize = cnf.num_variables();
+        let mut solver: CdclSolver = CdclSolver {
+            variables: cnf.variables().to_vec(),
+            anonymous: vec![false; num_variables],
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * num_variables],
+            assigns: vec![None; num_variables],
+            level: vec![0; num_variables],
+            reason: vec![None; num_variables],
+            phase: vec![false; num_variables],
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            propagated: 0,
+            activity: vec![0.0; num_variables],
+            var_increment: 1.0,
+            clause_increment: 1.0,
+            order: VarOrder::new(num_variables),
+            max_learned: (cnf.len() as f64 / 3.0).max(100.0),
+            num_learned: 0,
+            consistent: true,
+            assumptions: Vec::new(),
+            failed: Vec::new(),
+            stats: SolverStats::default(),
+            proof,
+        };
+        for var in 0..num_variables {
+            solver.order.insert(var, &solver.activity);
+        }
+        for clause in cnf.clauses() {
+            solver.add_clause(clause);
+        }
+        solver
+    }
+
+    // Names by index, empty for the variables made by new_variable
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn stats(&self) -> &SolverStats {
+        &self.stats
+    }
+
+    // None unless the solver was created with with_proof
+    pub fn proof(&self) -> Option<&DratProof> {
+        self.proof.as_ref()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<usize> {
+        (0..self.variables.len()).find(|&var| !self.anonymous[var] && self.variables[var] == name)
+    }
+
+    // Index of the variable called name, added as a new variable if the solver doesn't have it
+    pub fn add_variable(&mut self, name: &str) -> usize {
+        match self.variable(name) {
+            Some(var) => var,
+            None => self.push_variable(name.to_string(), false),
+        }
+    }
+
+    /*
+        Adds a variable without a name, for the selectors of the solvers built on this one.
+        Whatever the names of the formula, it is never returned by variable or add_variable
+        and it is left out of the models.
+    */
+    pub fn new_variable(&mut self) -> usize {
+        self.push_variable(String::new(), true)
+    }
+
+    fn push_variable(&mut self, name: String, anonymous: bool) -> usize {
+        let var: usize = self.variables.len();
+        self.variables.push(name);
+        self.anonymous.push(anonymous);
+        self.watches.extend([Vec::new(), Vec::new()]);
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.phase.push(false);
+        self.activity.push(0.0);
+        self.order.position.push(None);
+        self.order.insert(var, &self.activity);
+        var
+    }
+
+    /*
+        Adds a clause over the variables of the solver, between two calls to solve.
+        Literals already false without any decision are left out of it.
+    */
+    pub fn add_clause(&mut self, clause: &Clause) {
+        self.backtrack(0);
+        let mut clause: Clause = clause.clone();
+        clause.normalize();
+        if !self.consistent || clause.is_tautology() {
+            return;
+        }
+        let mut literals: Vec<Literal> = Vec::new();
+        for &literal in clause.literals() {
+            match self.value(literal) {
+                Some(true) => return,
+                Some(false) => {}
+                None => literals.push(literal),
+            }
+        }
+        match literals.len() {
+            0 => {
+                self.consistent = false;
+                self.add_lemma(&[]);
+            }
+            1 => self.enqueue(literals[0], None),
+            _ => {
+                self.store_clause(literals, false);
+            }
+        }
+    }
+
+    pub fn solve(&mut self) -> SatResult {
+        self.solve_with_assumptions(&[])
+    }
+
+    /*
+        Looks for a model where every assumption is true, without adding them as clauses:
+        the clauses learned on the way hold without them and are kept for the next calls.
+        When there is none, failed_assumptions gives the assumptions that caused it.
+    */
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> SatResult {
+        self.failed.clear();
+        if !self.consistent {
+            return SatResult::Unsat;
+        }
+        self.backtrack(0);
+        self.assumptions = assumptions.to_vec();
+        let mut restarts: u64 = 0;
+        loop {
+            let limit: u64 = luby(restarts) * RESTART_BASE;
+            match self.search(limit) {
+                Some(true) => {
+                    let values: Vec<bool> = self
+                        .assigns
+                        .iter()
+                        .map(|value| value.unwrap_or(false))
+                        .collect();
+                    self.backtrack(0);
+                    let model: Assignment<bool> = (0..values.len())
+                        .filter(|&var| !self.anonymous[var])
+                        .map(|var| (self.variables[var].clone(), values[var]))
+                        .collect();
+                    return SatResult::Sat(model);
+                }
+                Some(false) => {
+                    self.backtrack(0);
+                    return SatResult::Unsat;
+                }
+                None => {
+                    restarts += 1;
+                    self.stats.restarts += 1;
+                    self.backtrack(0);
+                }
+            }
+        }
+    }
+
+    // Some(true) on a model, Some(false) when unsatisfiable, None to restart
+    fn search(&mut self, conflict_limit: u64) -> Option<bool> {
+        let mut conflicts: u64 = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.stats.conflicts += 1;
+                conflicts += 1;
+                if self.decision_level() == 0 {
+                    self.consistent = false;
+                    self.add_lemma(&[]);
+                    return Some(false);
+                }
+                let (learned, backjump_level) = self.analyze(conflict);
+                self.add_lemma(&learned);
+                self.backtrack(backjump_level);
+                if learned.len() == 1 {
+                    self.enqueue(learned[0], None);
+                } else {
+                    let asserting: Literal = learned[0];
+                    let index: usize = self.store_clause(learned, true);
+                    self.bump_clause(index);
+                    self.enqueue(asserting, Some(index));
+                }
+                self.stats.learned += 1;
+                self.var_increment /= VAR_DECAY;
+                self.clause_increment /= CLAUSE_DECAY;
+                continue;
+            }
+
+            if conflicts >= conflict_limit {
+                return None;
+            }
+            if self.num_learned as f64 >= self.max_learned + self.trail.len() as f64 {
+                self.reduce_learned();
+            }
+
+            // Assumptions are the first decisions, one level each
+            while self.decision_level() < self.assumptions.len() {
+                let assumption: Literal = self.assumptions[self.decision_level()];
+                match self.value(assumption) {
+                    Some(true) => self.trail_limits.push(self.trail.len()),
+                    Some(false) => {
+                        self.failed = self.analyze_final(assumption);
+                        return Some(false);
+                    }
+                    None => break,
+                }
+            }
+            if self.decision_level() < self.assumptions.len() {
+                let assumption: Literal = self.assumptions[self.decision_level()];
+                self.trail_limits.push(self.trail.len());
+                self.enqueue(assumption, None);
+                continue;
+            }
+
+            match self.pick_branch_variable() {
+                None => return Some(true),
+                Some(var) => {
+                    self.stats.decisions += 1;
+                    self.trail_limits.push(self.trail.len());
+                    self.enqueue(Literal::new(var, !self.phase[var]), None);
+                }
+            }
+        }
+    }
+
+    /*
+        Subset of the assumptions that together make the assumption falsified false:
+        the decisions its negation was implied from, which are all assumptions.
+    */
+    fn analyze_final(&self, falsified: Literal) -> Vec<Literal> {
+        let mut failed: Vec<Literal> = vec![falsified];
+        if self.level[falsified.var()] == 0 {
+            return failed;
+        }
+        let mut seen: Vec<bool> = vec![false; self.assigns.len()];
+        seen[falsified.var()] = true;
+        for &literal in self.trail[self.trail_limits[0]..].iter().rev() {
+            let var: usize = literal.var();
+            if !seen[var] {
+                continue;
+            }
+            match self.reason[var] {
+                None => failed.push(literal),
+                Some(reason) => {
+                    for other in &self.clauses[reason].literals[1..] {
+                        if self.level[other.var()] > 0 {
+                            seen[other.var()] = true;
+                        }
+                    }
+                }
+            }
+        }
+        failed
+    }
+
+    // Assumptions of the last solve that made it unsatisfiable, empty if the clauses alone are
+    pub fn failed_assumptions(&self) -> &[Literal] {
+        &self.failed
+    }
+
+    fn value(&self, literal: Literal) -> Option<bool> {
+        literal_value(&self.assigns, literal)
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    // Makes literal true at the current level, reason is the clause that implied it
+    fn enqueue(&mut self, literal: Literal, reason: Option<usize>) {
+        let var: usize = literal.var();
+        self.assigns[var] = Some(!literal.is_negated());
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(literal);
+    }
+
+    fn store_clause(&mut self, literals: Vec<Literal>, learned: bool) -> usize {
+        let index: usize = self.clauses.len();
+        self.num_learned += learned as usize;
+        self.watches[literals[0].code()].push(index);
+        self.watches[literals[1].code()].push(index);
+        self.clauses.push(StoredClause {
+            literals,
+            learned,
+            activity: 0.0,
+            deleted: false,
+        });
+        index
+    }
+
+    fn add_lemma(&mut self, literals: &[Literal]) {
+        if let Some(proof) = &mut self.proof {
+            proof.add(literals.iter().copied().collect());
+        }
+    }
+
+    // Unassigns everything above level, saving the values as the phases of the variables
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start: usize = self.trail_limits[level];
+        for literal in self.trail.drain(start..) {
+            let var: usize = literal.var();
+            self.phase[var] = !literal.is_negated();
+            self.assigns[var] = None;
+            self.reason[var] = None;
+            self.order.insert(var, &self.activity);
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    // Runs every pending assignment through the watches, returns the conflicting clause if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_literal: Literal = !self.trail[self.propagated];
+            self.propagated += 1;
+            self.stats.propagations += 1;
+
+            let mut watchers: Vec<usize> = std::mem::take(&mut self.watches[false_literal.code()]);
+            let mut kept: usize = 0;
+            let mut i: usize = 0;
+            while i < watchers.len() {
+                let index: usize = watchers[i];
+                i += 1;
+                let clause: &mut StoredClause = &mut self.clauses[index];
+                // The false literal goes second, so the first one is the other watch
+                if clause.literals[0] == false_literal {
+                    clause.literals.swap(0, 1);
+                }
+                let first: Literal = clause.literals[0];
+                if literal_value(&self.assigns, first) == Some(true) {
+                    watchers[kept] = index;
+                    kept += 1;
+                    continue;
+                }
+
+                // Another literal that isn't false can be watched instead
+                let replacement: Option<usize> = (2..clause.literals.len())
+                    .find(|&k| literal_value(&self.assigns, clause.literals[k]) != Some(false));
+                if let Some(k) = replacement {
+                    clause.literals.swap(1, k);
+                    let watched: Literal = clause.literals[1];
+                    self.watches[watched.code()].push(index);
+                    continue;
+                }
+
+                watchers[kept] = index;
+                kept += 1;
+                if literal_value(&self.assigns, first) == Some(false) {
+                    // Conflict: the watchers not visited yet stay in the list
+                    while i < watchers.len() {
+                        watchers[kept] = watchers[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                    watchers.truncate(kept);
+                    self.watches[false_literal.code()] = watchers;
+                    self.propagated = self.trail.len();
+                    return Some(index);
+                }
+                self.enqueue(first, Some(index));
+            }
+            watchers.truncate(kept);
+            self.watches[false_literal.code()] = watchers;
+        }
+        None
+    }
+
+    /*
+        Resolves the conflicting clause with the reasons of its literals of the current level,
+        latest first, until a single one is left: the first unique implication point.
+        Returns the learned clause, with the negation of that literal first and
+        a literal of the highest level below second, and the level to jump back to.
+    */
+    fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize) {
+        let mut seen: Vec<bool> = vec![false; self.assigns.len()];
+        // The first literal is replaced by the asserting one at the end
+        let mut learned: Vec<Literal> = vec![Literal::positive(0)];
+        let mut pending: usize = 0;
+        let mut index: usize = self.trail.len();
+        let mut clause_index: usize = conflict;
+        let mut implied: Option<Literal> = None;
+
+        loop {
+            if self.clauses[clause_index].learned {
+                self.bump_clause(clause_index);
+            }
+            // The implied literal of a reason is first and already handled
+            let skip: usize = implied.is_some() as usize;
+            let literals: Vec<Literal> = self.clauses[clause_index].literals[skip..].to_vec();
+            for literal in literals {
+                let var: usize = literal.var();
+                if seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump_var(var);
+                if self.level[var] == self.decision_level() {
+                    pending += 1;
+                } else {
+                    learned.push(literal);
+                }
+            }
+
+            // Latest literal of the trail in the clause so far
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let literal: Literal = self.trail[index];
+            seen[literal.var()] = false;
+            pending -= 1;
+            if pending == 0 {
+                learned[0] = !literal;
+                break;
+            }
+            implied = Some(literal);
+            clause_index = self.reason[literal.var()].unwrap();
+        }
+
+        // A literal implied by other literals of the clause adds nothing to it
+        let minimized: Vec<Literal> = learned
+            .iter()
+            .enumerate()
+            .filter(|&(i, literal)| {
+                i == 0
+                    || match self.reason[literal.var()] {
+                        None => true,
+                        Some(reason) => self.clauses[reason].literals[1..]
+                            .iter()
+                            .any(|other| !seen[other.var()] && self.level[other.var()] > 0),
+                    }
+            })
+            .map(|(_, literal)| *literal)
+            .collect();
+        let mut learned: Vec<Literal> = minimized;
+
+        let mut backjump_level: usize = 0;
+        if learned.len() > 1 {
+            let highest: usize = (1..learned.len())
+                .max_by_key(|&i| self.level[learned[i].var()])
+                .unwrap();
+            learned.swap(1, highest);
+            backjump_level = self.level[learned[1].var()];
+        }
+        (learned, backjump_level)
+    }
+
+    fn bump_var(&mut self, var: usize) {
+        self.activity[var] += self.var_increment;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.var_increment *= 1e-100;
+        }
+        self.order.increase(var, &self.activity);
+    }
+
+    fn bump_clause(&mut self, index: usize) {
+        self.clauses[index].activity += self.clause_increment;
+        if self.clauses[index].activity > 1e20 {
+            for clause in self.clauses.iter_mut().filter(|clause| clause.learned) {
+                clause.activity *= 1e-20;
+            }
+            self.clause_increment *= 1e-20;
+        }
+    }
+
+    fn pick_branch_variable(&mut self) -> Option<usize> {
+        while let Some(var) = self.order.pop(&self.activity) {
+            if self.assigns[var].is_none() {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    /*
+        Deletes the least active half of the learned clauses, except binary ones
+        and those that are the reason of a current assignment.
+    */
+    fn reduce_learned(&mut self) {
+        let mut learned: Vec<usize> = (0..self.clauses.len())
+            .filter(|&index| self.clauses[index].learned)
+            .collect();
+        learned.sort_by(|&a, &b| {
+            self.clauses[a]
+                .activity
+                .total_cmp(&self.clauses[b].activity)
+        });
+        for &index in &learned[..learned.len() / 2] {
+            let first: Literal = self.clauses[index].literals[0];
+            let locked: bool =
+                self.reason[first.var()] == Some(index) && self.value(first) == Some(true);
+            if self.clauses[index].literals.len() > 2 && !locked {
+                self.clauses[index].deleted = true;
+                let literals: Vec<Literal> = std::mem::take(&mut self.clauses[index].literals);
+                if let Some(proof) = &mut self.proof {
+                    proof.delete(literals.into_iter().collect());
+                }
+                self.num_learned -= 1;
+                self.stats.deleted += 1;
+            }
+        }
+        self.compact();
+        self.max_learned *= LEARNED_GROWTH;
+    }
+
+    /*
+        Removes the deleted clauses, so the clause list doesn't grow with every reduction,
+        and renumbers the watches and reasons pointing after them.
+    */
+    fn compact(&mut self) {
+        let mut remap: Vec<Option<usize>> = Vec::with_capacity(self.clauses.len());
+        let mut kept: usize = 0;
+        for clause in &self.clauses {
+            if clause.deleted {
+                remap.push(None);
+            } else {
+                remap.push(Some(kept));
+                kept += 1;
+            }
+        }
+        self.clauses.retain(|clause| !clause.deleted);
+        for watchers in self.watches.iter_mut() {
+            watchers.retain_mut(|index| match remap[*index] {
+                Some(new_index) => {
+                    *index = new_index;
+                    true
+                }
+                None => false,
+            });
+        }
+        // Locked clauses are kept, only unassigned variables can have a deleted reason
+        for reason in self.reason.iter_mut() {
+            *reason = reason.and_then(|index| remap[index]);
+        }
+    }
+}
+
+impl Default for CdclSolver {
+    fn default() -> Self {
+        CdclSolver::new(&Cnf::new())
+    }
+}
+
+// Each formula gets a new solver, so its stats and proof are those of the last one
+impl Solver for CdclSolver {
+    fn solve_cnf(&mut self, cnf: &Cnf) -> SatResult {
+        let proof: Option<DratProof> = self.proof.as_ref().map(|_| DratProof::new());
+        *self = CdclSolver::build(cnf, proof);
+        self.solve()
+    }
+}
+
+impl Cnf {
+    pub fn cdcl(&self) -> SatResult {
+        CdclSolver::new(self).solve()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aux::ExpressionEvaluator;
+    use crate::boolean_operations::sat::test_utils::random_ksat;
+    use crate::boolean_operations::BooleanOperations;
+
+    fn pigeonhole(pigeons: usize, holes: usize) -> Cnf {
+        let mut cnf: Cnf = Cnf::new();
+        for i in 0..pigeons {
+            for h in 0..holes {
+                cnf.add_variable(&format!("p_{}_{}", i, h));
+            }
+        }
+        let var = |i: usize, h: usize| i * holes + h;
+        for i in 0..pigeons {
+            cnf.add_clause((0..holes).map(|h| Literal::positive(var(i, h))).collect());
+        }
+        for h in 0..holes {
+            for i in 0..pigeons {
+                for j in i + 1..pigeons {
+                    cnf.add_clause(Clause::new(vec![
+                        Literal::negative(var(i, h)),
+                        Literal::negative(var(j, h)),
+                    ]));
+                }
+            }
+        }
+        cnf
+    }
+
+    #[test]
+    fn test_luby() {
+        let sequence: Vec<u64> = (0..15).map(luby).collect();
+        assert_eq!(sequence, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_matches_dpll() {
+        // Around 4.26 clauses per variable, about half of them satisfiable
+        for seed in 1..40 {
+            let cnf: Cnf = random_ksat(seed * 7919, 20, 85, 3);
+            let result: SatResult = cnf.cdcl();
+            assert_eq!(result.is_sat(), cnf.dpll().is_sat(), "seed {}", seed);
+            if let Some(model) = result.model() {
+                assert!(cnf.evaluate(model.values()), "seed {}", seed);
+                assert_eq!(model.len(), 20);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pigeonhole() {
+        let mut solver: CdclSolver = CdclSolver::new(&pigeonhole(8, 7));
+        assert_eq!(solver.solve(), SatResult::Unsat);
+        let stats: &SolverStats = solver.stats();
+        assert!(stats.conflicts > 0 && stats.decisions > 0 && stats.propagations > 0);
+        assert_eq!(stats.learned, stats.conflicts - 1);
+        assert!(stats.restarts > 0 && stats.deleted > 0);
+        // Deleted clauses are gone from the list and the count of learned ones is exact
+        assert!(solver.clauses.iter().all(|clause| !clause.deleted));
+        assert_eq!(
+            solver.num_learned,
+            solver
+                .clauses
+                .iter()
+                .filter(|clause| clause.learned)
+                .count()
+        );
+
+        let cnf: Cnf = pigeonhole(9, 9);
+        let result: SatResult = cnf.cdcl();
+        assert!(cnf.evaluate(result.model().unwrap().values()));
+    }
+
+    #[test]
+    fn test_units_and_empty() {
+        assert_eq!(Cnf::from_rpn("AA!&").unwrap().cdcl(), SatResult::Unsat);
+        assert_eq!(Cnf::from_rpn("A0&").unwrap().cdcl(), SatResult::Unsat);
+        assert!(Cnf::from_rpn("1").unwrap().cdcl().is_sat());
+        let result: SatResult = Cnf::from_rpn("AB!&B!C|&").unwrap().cdcl();
+        assert_eq!(result.model().unwrap().values(), &vec![true, false, false]);
+
+        // Clauses can be added after a model was found
+        let mut solver: CdclSolver = CdclSolver::new(&Cnf::from_rpn("AB|").unwrap());
+        assert!(solver.solve().is_sat());
+        solver.add_clause(&Clause::new(vec![Literal::negative(0)]));
+        solver.add_clause(&Clause::new(vec![Literal::negative(1)]));
+        assert_eq!(solver.solve(), SatResult::Unsat);
+        assert_eq!(solver.stats().decisions, 1);
+    }
+
+    #[test]
+    fn test_tseitin_and_dimacs() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // x0 = x1 = ... = x29 is always satisfiable, with its negation too
+        let names: Vec<String> = (0..30).map(|i| format!("x{}", i)).collect();
+        let formula: String = evaluator.infix_to_rpn(&names.join(" = ")).unwrap();
+        for formula in [formula.clone(), format!("{}!", formula)] {
+            let cnf: Cnf = evaluator
+                .tseitin_transformation(&formula, true)
+                .unwrap()
+                .to_cnf();
+            let result: SatResult = cnf.cdcl();
+            assert!(cnf.evaluate(result.model().unwrap().values()));
+        }
+        // Three variables can't all be different from each other
+        let formula: String = evaluator
+            .infix_to_rpn("(A ^ B) & (B ^ C) & (A ^ C)")
+            .unwrap();
+        let cnf: Cnf = evaluator
+            .tseitin_transformation(&formula, false)
+            .unwrap()
+            .to_cnf();
+        let cnf: Cnf = Cnf::from_dimacs(&cnf.to_dimacs()).unwrap();
+        assert_eq!(cnf.cdcl(), SatResult::Unsat);
+    }
+}
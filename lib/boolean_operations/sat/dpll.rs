@@ -0,0 +1,236 @@
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal};
+use crate::boolean_operations::sat::sat::{SatResult, Solver};
+
+/*
+    Davis-Putnam-Logemann-Loveland search over the clauses of a Cnf.
+    Every decision is followed by unit propagation and pure literal elimination,
+    and the values set since a decision are undone from the trail when it fails.
+*/
+struct Dpll<'a> {
+    clauses: &'a [Clause],
+    values: Vec<Option<bool>>,
+    trail: Vec<usize>,
+}
+
+impl<'a> Dpll<'a> {
+    fn new(cnf: &'a Cnf) -> Self {
+        Dpll {
+            clauses: cnf.clauses(),
+            values: vec![None; cnf.num_variables()],
+            trail: Vec::new(),
+        }
+    }
+
+    fn value(&self, literal: Literal) -> Option<bool> {
+        self.values[literal.var()].map(|value| literal.value(value))
+    }
+
+    // Makes literal true
+    fn assign(&mut self, literal: Literal) {
+        self.values[literal.var()] = Some(!literal.is_negated());
+        self.trail.push(literal.var());
+    }
+
+    fn undo(&mut self, mark: usize) {
+        for var in self.trail.drain(mark..) {
+            self.values[var] = None;
+        }
+    }
+
+    fn is_satisfied(&self, clause: &Clause) -> bool {
+        clause
+            .literals()
+            .iter()
+            .any(|&literal| self.value(literal) == Some(true))
+    }
+
+    // Assigns the last literal of every unit clause until none is left, false on a conflict
+    fn unit_propagation(&mut self) -> bool {
+        let mut changed: bool = true;
+        while changed {
+            changed = false;
+            for clause in self.clauses {
+                if self.is_satisfied(clause) {
+                    continue;
+                }
+                let mut unassigned = clause
+                    .literals()
+                    .iter()
+                    .filter(|&&literal| self.value(literal).is_none());
+                match (unassigned.next(), unassigned.next()) {
+                    (None, _) => return false,
+                    (Some(&literal), None) => {
+                        self.assign(literal);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+
+    /*
+        A variable that only appears with one sign in the clauses not satisfied yet
+        can be given the value that satisfies all of them without losing any model.
+    */
+    fn pure_literal_elimination(&mut self) -> bool {
+        // Bit 0: seen as a positive literal, bit 1: seen negated
+        let mut signs: Vec<u8> = vec![0; self.values.len()];
+        for clause in self.clauses {
+            if self.is_satisfied(clause) {
+                continue;
+            }
+            for &literal in clause.literals() {
+                if self.value(literal).is_none() {
+                    signs[literal.var()] |= 1 << literal.is_negated() as u8;
+                }
+            }
+        }
+        let mut assigned: bool = false;
+        for (var, sign) in signs.into_iter().enumerate() {
+            if sign == 1 || sign == 2 {
+                self.assign(Literal::new(var, sign == 2));
+                assigned = true;
+            }
+        }
+        assigned
+    }
+
+    // First unassigned literal of the shortest clause not satisfied yet
+    fn choose_literal(&self) -> Option<Literal> {
+        self.clauses
+            .iter()
+            .filter(|clause| !self.is_satisfied(clause))
+            .map(|clause| {
+                clause
+                    .literals()
+                    .iter()
+                    .filter(|&&literal| self.value(literal).is_none())
+                    .collect::<Vec<&Literal>>()
+            })
+            .min_by_key(|literals| literals.len())
+            .and_then(|literals| literals.first().copied().copied())
+    }
+
+    fn search(&mut self) -> bool {
+        let mark: usize = self.trail.len();
+        loop {
+            if !self.unit_propagation() {
+                self.undo(mark);
+                return false;
+            }
+            if !self.pure_literal_elimination() {
+                break;
+            }
+        }
+
+        let literal: Literal = match self.choose_literal() {
+            Some(literal) => literal,
+            // Every clause is satisfied
+            None => return true,
+        };
+        let decision_mark: usize = self.trail.len();
+        for decision in [literal, !literal] {
+            self.assign(decision);
+            if self.search() {
+                return true;
+            }
+            self.undo(decision_mark);
+        }
+        self.undo(mark);
+        false
+    }
+}
+
+// DPLL behind the Solver trait, it keeps nothing between two formulas
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DpllSolver;
+
+impl Solver for DpllSolver {
+    fn solve_cnf(&mut self, cnf: &Cnf) -> SatResult {
+        cnf.dpll()
+    }
+}
+
+impl Cnf {
+    // Variables left unassigned once every clause is satisfied are false in the model
+    pub fn dpll(&self) -> SatResult {
+        let mut solver: Dpll = Dpll::new(self);
+        if solver.search() {
+            let values: Vec<bool> = solver
+                .values
+                .iter()
+                .map(|value| value.unwrap_or(false))
+                .collect();
+            SatResult::Sat(self.assignment(&values))
+        } else {
+            SatResult::Unsat
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aux::Assignment;
+
+    fn model_values(result: &SatResult) -> Vec<bool> {
+        result.model().unwrap().values().clone()
+    }
+
+    #[test]
+    fn test_dpll() {
+        let cnf: Cnf = Cnf::from_rpn("AB|A!C|&B!C!|&").unwrap();
+        let result: SatResult = cnf.dpll();
+        assert!(result.is_sat());
+        assert!(cnf.evaluate(&model_values(&result)));
+
+        assert_eq!(Cnf::from_rpn("AA!&").unwrap().dpll(), SatResult::Unsat);
+        assert_eq!(Cnf::from_rpn("A0&").unwrap().dpll(), SatResult::Unsat);
+        assert_eq!(
+            Cnf::from_rpn("1").unwrap().dpll(),
+            SatResult::Sat(Assignment::new())
+        );
+        // Every clause of two variables, no model
+        assert_eq!(
+            Cnf::from_rpn("AB|A!B|&AB!|&A!B!|&").unwrap().dpll(),
+            SatResult::Unsat
+        );
+    }
+
+    #[test]
+    fn test_pigeonhole() {
+        // 6 pigeons in 5 holes, p_i_h is pigeon i in hole h
+        let (pigeons, holes) = (6, 5);
+        let mut clauses: Vec<Vec<(String, bool)>> = Vec::new();
+        for i in 0..pigeons {
+            clauses.push(
+                (0..holes)
+                    .map(|h| (format!("p_{}_{}", i, h), false))
+                    .collect(),
+            );
+        }
+        for h in 0..holes {
+            for i in 0..pigeons {
+                for j in i + 1..pigeons {
+                    clauses.push(vec![
+                        (format!("p_{}_{}", i, h), true),
+                        (format!("p_{}_{}", j, h), true),
+                    ]);
+                }
+            }
+        }
+        assert_eq!(Cnf::from_named_clauses(&clauses).dpll(), SatResult::Unsat);
+
+        // One less pigeon fits, far past the size of a truth table
+        let clauses: Vec<Vec<(String, bool)>> = clauses
+            .into_iter()
+            .filter(|clause| clause.iter().all(|(name, _)| !name.starts_with("p_5_")))
+            .collect();
+        let cnf: Cnf = Cnf::from_named_clauses(&clauses);
+        assert_eq!(cnf.num_variables(), 25);
+        let result: SatResult = cnf.dpll();
+        assert!(cnf.evaluate(&model_values(&result)));
+    }
+}
@@ -0,0 +1,499 @@
+use crate::aux::{ExpressionEvaluator, ParseError};
+use crate::boolean_operations::cnf::dimacs::dimacs_literal;
+use crate::boolean_operations::cnf::{Clause, Cnf, DimacsError, Literal, TseitinCnf};
+use crate::boolean_operations::sat::cdcl::CdclSolver;
+use crate::boolean_operations::sat::sat::SatResult;
+use crate::boolean_operations::BooleanOperations;
+use std::collections::HashMap;
+use std::fmt;
+
+// A clause added to the formula, which must follow from it, or removed from it
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofStep {
+    Add(Clause),
+    Delete(Clause),
+}
+
+/*
+    Sequence of clause additions and deletions that ends with the empty clause,
+    so checking every addition checks that the formula is unsatisfiable.
+    Written as a DRAT file: one clause per line in DIMACS numbering, deletions start with d.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DratProof {
+    steps: Vec<ProofStep>,
+}
+
+impl DratProof {
+    pub fn new() -> Self {
+        DratProof { steps: Vec::new() }
+    }
+
+    pub fn steps(&self) -> &[ProofStep] {
+        &self.steps
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn add(&mut self, lemma: Clause) {
+        self.steps.push(ProofStep::Add(lemma));
+    }
+
+    pub fn delete(&mut self, clause: Clause) {
+        self.steps.push(ProofStep::Delete(clause));
+    }
+
+    pub fn to_drat(&self) -> String {
+        let mut drat: String = String::new();
+        for step in &self.steps {
+            let clause: &Clause = match step {
+                ProofStep::Add(lemma) => lemma,
+                ProofStep::Delete(clause) => {
+                    drat.push_str("d ");
+                    clause
+                }
+            };
+            for literal in clause.literals() {
+                drat.push_str(&format!("{} ", dimacs_literal(*literal)));
+            }
+            drat.push_str("0\n");
+        }
+        drat
+    }
+
+    // Reads a DRAT file as written by other solvers, comments start with c
+    pub fn from_drat(input: &str) -> Result<Self, DimacsError> {
+        let mut proof: DratProof = DratProof::new();
+        let mut clause: Clause = Clause::default();
+        let mut deletion: bool = false;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number: usize = index + 1;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.first() == Some(&"c") {
+                continue;
+            }
+            for token in tokens {
+                if token == "d" && clause.is_empty() && !deletion {
+                    deletion = true;
+                    continue;
+                }
+                let value: i64 = token.parse().map_err(|_| DimacsError::InvalidToken {
+                    line: line_number,
+                    snippet: token.to_string(),
+                })?;
+                if value != 0 {
+                    let var: usize = value.unsigned_abs() as usize;
+                    clause.push(Literal::new(var - 1, value < 0));
+                    continue;
+                }
+                let clause: Clause = std::mem::take(&mut clause);
+                if deletion {
+                    proof.delete(clause);
+                } else {
+                    proof.add(clause);
+                }
+                deletion = false;
+            }
+        }
+        if !clause.is_empty() || deletion {
+            return Err(DimacsError::InvalidToken {
+                line: input.lines().count(),
+                snippet: "missing 0".to_string(),
+            });
+        }
+        Ok(proof)
+    }
+}
+
+// Why a proof doesn't show that a Cnf is unsatisfiable
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofError {
+    // Step, from 0, adding a clause that neither unit propagation nor RAT justifies
+    NotImplied { step: usize, lemma: Clause },
+    MissingEmptyClause,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProofError::NotImplied { step, lemma } => {
+                let literals: Vec<String> = lemma
+                    .literals()
+                    .iter()
+                    .map(|literal| dimacs_literal(*literal).to_string())
+                    .collect();
+                write!(
+                    f,
+                    "Error: Lemma '{} 0' at step {} doesn't follow from the clauses before it",
+                    literals.join(" "),
+                    step
+                )
+            }
+            ProofError::MissingEmptyClause => {
+                write!(f, "Error: The proof never adds the empty clause")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+/*
+    Clauses of the formula and of the proof so far, with the clauses each literal
+    appears in. A clause only becomes unit or false when one of its literals becomes false,
+    so propagation starts from the unit clauses and then visits the occurrences
+    of the literals it falsifies.
+*/
+struct Checker {
+    clauses: Vec<Option<Clause>>,
+    occurrences: Vec<Vec<usize>>,
+    // Indices of the clauses still in the formula, by their sorted literals
+    index: HashMap<Clause, Vec<usize>>,
+    units: Vec<usize>,
+}
+
+impl Checker {
+    fn new(num_variables: usize) -> Self {
+        Checker {
+            clauses: Vec::new(),
+            occurrences: vec![Vec::new(); 2 * num_variables],
+            index: HashMap::new(),
+            units: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, clause: &Clause) {
+        let position: usize = self.clauses.len();
+        for literal in clause.literals() {
+            self.occurrences[literal.code()].push(position);
+        }
+        if clause.len() <= 1 {
+            self.units.push(position);
+        }
+        self.index.entry(sorted(clause)).or_default().push(position);
+        self.clauses.push(Some(clause.clone()));
+    }
+
+    // Deleting a clause that isn't there is ignored, as it can only make the check harder
+    fn remove(&mut self, clause: &Clause) {
+        if let Some(positions) = self.index.get_mut(&sorted(clause)) {
+            if let Some(position) = positions.pop() {
+                self.clauses[position] = None;
+            }
+        }
+    }
+
+    // Reverse unit propagation: the negation of the lemma propagates to a conflict
+    fn implies(&self, lemma: &[Literal]) -> bool {
+        let mut assigns: Vec<Option<bool>> = vec![None; self.occurrences.len() / 2];
+        let mut trail: Vec<Literal> = Vec::new();
+        for &literal in lemma {
+            if !assign(!literal, &mut assigns, &mut trail) {
+                return true;
+            }
+        }
+        for &position in &self.units {
+            if let Some(clause) = &self.clauses[position] {
+                match clause.literals().first() {
+                    None => return true,
+                    Some(&literal) => {
+                        if !assign(literal, &mut assigns, &mut trail) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut propagated: usize = 0;
+        while propagated < trail.len() {
+            let false_literal: Literal = !trail[propagated];
+            propagated += 1;
+            for &position in &self.occurrences[false_literal.code()] {
+                let clause: &Clause = match &self.clauses[position] {
+                    Some(clause) => clause,
+                    None => continue,
+                };
+                let mut unassigned: Option<Literal> = None;
+                let mut open: usize = 0;
+                let mut satisfied: bool = false;
+                for &literal in clause.literals() {
+                    match assigns[literal.var()] {
+                        Some(value) if literal.value(value) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(_) => {}
+                        None => {
+                            unassigned = Some(literal);
+                            open += 1;
+                        }
+                    }
+                }
+                if satisfied || open > 1 {
+                    continue;
+                }
+                match unassigned {
+                    None => return true,
+                    Some(literal) => {
+                        assign(literal, &mut assigns, &mut trail);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /*
+        Resolution asymmetric tautology on the first literal of the lemma: every resolvent
+        with a clause containing its negation follows by unit propagation.
+    */
+    fn implies_rat(&self, lemma: &[Literal]) -> bool {
+        let pivot: Literal = match lemma.first() {
+            Some(&pivot) => pivot,
+            None => return false,
+        };
+        self.occurrences[(!pivot).code()]
+            .iter()
+            .all(|&position| match &self.clauses[position] {
+                None => true,
+                Some(clause) => {
+                    let mut resolvent: Vec<Literal> = lemma.to_vec();
+                    resolvent.extend(
+                        clause
+                            .literals()
+                            .iter()
+                            .filter(|&&literal| literal != !pivot),
+                    );
+                    self.implies(&resolvent)
+                }
+            })
+    }
+}
+
+// Makes literal true, false when it already is false, that is on a conflict
+fn assign(literal: Literal, assigns: &mut [Option<bool>], trail: &mut Vec<Literal>) -> bool {
+    match assigns[literal.var()] {
+        Some(value) => literal.value(value),
+        None => {
+            assigns[literal.var()] = Some(!literal.is_negated());
+            trail.push(literal);
+            true
+        }
+    }
+}
+
+fn sorted(clause: &Clause) -> Clause {
+    let mut literals: Vec<Literal> = clause.literals().to_vec();
+    literals.sort();
+    literals.into_iter().collect()
+}
+
+impl Cnf {
+    /*
+        Checks every clause the proof adds against the clauses of the Cnf and those
+        added and not deleted before it, by reverse unit propagation or else as a RAT
+        on its first literal, until it adds the empty clause.
+    */
+    pub fn check_proof(&self, proof: &DratProof) -> Result<(), ProofError> {
+        let proof_variables: usize = proof
+            .steps()
+            .iter()
+            .flat_map(|step| match step {
+                ProofStep::Add(clause) | ProofStep::Delete(clause) => clause.literals(),
+            })
+            .map(|literal| literal.var() + 1)
+            .max()
+            .unwrap_or(0);
+        let mut checker: Checker = Checker::new(self.num_variables().max(proof_variables));
+        for clause in self.clauses() {
+            checker.insert(clause);
+        }
+
+        for (step, proof_step) in proof.steps().iter().enumerate() {
+            match proof_step {
+                ProofStep::Delete(clause) => checker.remove(clause),
+                ProofStep::Add(lemma) => {
+                    if !checker.implies(lemma.literals()) && !checker.implies_rat(lemma.literals())
+                    {
+                        return Err(ProofError::NotImplied {
+                            step,
+                            lemma: lemma.clone(),
+                        });
+                    }
+                    if lemma.is_empty() {
+                        return Ok(());
+                    }
+                    checker.insert(lemma);
+                }
+            }
+        }
+        Err(ProofError::MissingEmptyClause)
+    }
+
+    // The CDCL result, with the proof that there is no model when it is Unsat
+    pub fn cdcl_with_proof(&self) -> (SatResult, DratProof) {
+        let mut solver: CdclSolver = CdclSolver::with_proof(self);
+        let result: SatResult = solver.solve();
+        (result, solver.proof().cloned().unwrap_or_default())
+    }
+}
+
+impl ExpressionEvaluator<bool, BooleanOperations> {
+    /*
+        For a formula without any model, the Cnf it is encoded into and a proof that
+        the Cnf is unsatisfiable, for Cnf::check_proof or an external DRAT checker.
+        None when the formula is satisfiable.
+    */
+    pub fn unsat_proof(&self, formula: &str) -> Result<Option<(Cnf, DratProof)>, ParseError> {
+        let encoding: TseitinCnf = self.tseitin_transformation(formula, true)?;
+        let cnf: Cnf = encoding.to_cnf();
+        let (result, proof) = cnf.cdcl_with_proof();
+        Ok(match result {
+            SatResult::Unsat => Some((cnf, proof)),
+            SatResult::Sat(_) | SatResult::Unknown => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // n + 1 pigeons in n holes, variable p * n + h puts pigeon p in hole h
+    fn pigeonhole(holes: usize) -> Cnf {
+        let variables: Vec<String> = (0..(holes + 1) * holes)
+            .map(|var| format!("p{}h{}", var / holes, var % holes))
+            .collect();
+        let mut cnf: Cnf = Cnf::with_variables(variables);
+        for pigeon in 0..=holes {
+            cnf.add_clause(
+                (0..holes)
+                    .map(|hole| Literal::positive(pigeon * holes + hole))
+                    .collect(),
+            );
+        }
+        for hole in 0..holes {
+            for first in 0..=holes {
+                for second in first + 1..=holes {
+                    cnf.add_clause(Clause::new(vec![
+                        Literal::negative(first * holes + hole),
+                        Literal::negative(second * holes + hole),
+                    ]));
+                }
+            }
+        }
+        cnf
+    }
+
+    #[test]
+    fn test_solver_proofs() {
+        for holes in 2..6 {
+            let cnf: Cnf = pigeonhole(holes);
+            let (result, proof) = cnf.cdcl_with_proof();
+            assert_eq!(result, SatResult::Unsat);
+            assert_eq!(
+                proof.steps().last(),
+                Some(&ProofStep::Add(Clause::default()))
+            );
+            assert_eq!(cnf.check_proof(&proof), Ok(()));
+
+            // The same proof after a round trip through the DRAT format
+            let read: DratProof = DratProof::from_drat(&proof.to_drat()).unwrap();
+            assert_eq!(read, proof);
+            assert_eq!(cnf.check_proof(&read), Ok(()));
+        }
+
+        let (result, proof) = Cnf::from_rpn("AB|A!B|&").unwrap().cdcl_with_proof();
+        assert!(result.is_sat());
+        assert_eq!(
+            Cnf::from_rpn("AB|A!B|&").unwrap().check_proof(&proof),
+            Err(ProofError::MissingEmptyClause)
+        );
+    }
+
+    #[test]
+    fn test_wrong_proofs() {
+        // (A | B) & (!A | B) & (A | !B) & (!A | !B)
+        let cnf: Cnf = Cnf::from_rpn("AB|A!B|&AB!|&A!B!|&").unwrap();
+        let proof: DratProof = DratProof::from_drat("c B holds\n2 0\n0\n").unwrap();
+        assert_eq!(cnf.check_proof(&proof), Ok(()));
+
+        // The empty clause doesn't follow from the formula alone
+        let proof: DratProof = DratProof::from_drat("0\n").unwrap();
+        assert_eq!(
+            cnf.check_proof(&proof),
+            Err(ProofError::NotImplied {
+                step: 0,
+                lemma: Clause::default()
+            })
+        );
+
+        // Once (A | B) is deleted, B no longer follows
+        let proof: DratProof = DratProof::from_drat("d 1 2 0\n2 0\n0\n").unwrap();
+        assert_eq!(
+            cnf.check_proof(&proof),
+            Err(ProofError::NotImplied {
+                step: 1,
+                lemma: Clause::new(vec![Literal::positive(1)])
+            })
+        );
+
+        assert_eq!(
+            DratProof::from_drat("1 x 0\n"),
+            Err(DimacsError::InvalidToken {
+                line: 1,
+                snippet: "x".to_string()
+            })
+        );
+        assert!(DratProof::from_drat("1 2\n").is_err());
+    }
+
+    #[test]
+    fn test_rat() {
+        /*
+            C is a fresh variable, so C | A is a RAT on C without following by propagation,
+            and !A follows once it is there. The formula has a model with B, so only
+            the empty clause is rejected.
+        */
+        let cnf: Cnf = Cnf::from_rpn("AB|A!B|&A!B!|&").unwrap();
+        let mut proof: DratProof = DratProof::new();
+        proof.add(Clause::new(vec![
+            Literal::positive(2),
+            Literal::positive(0),
+        ]));
+        proof.add(Clause::new(vec![Literal::negative(0)]));
+        proof.add(Clause::default());
+        assert_eq!(
+            cnf.check_proof(&proof),
+            Err(ProofError::NotImplied {
+                step: 2,
+                lemma: Clause::default()
+            })
+        );
+        assert!(cnf
+            .check_proof(&proof)
+            .unwrap_err()
+            .to_string()
+            .contains("step 2"));
+    }
+
+    #[test]
+    fn test_formula() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formula: String = evaluator
+            .infix_to_rpn("(A ^ B) & (B ^ C) & (A ^ C)")
+            .unwrap();
+        let (cnf, proof) = evaluator.unsat_proof(&formula).unwrap().unwrap();
+        assert_eq!(cnf.check_proof(&proof), Ok(()));
+        assert_eq!(evaluator.unsat_proof("AB^").unwrap(), None);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::boolean_operations::cnf::{Cnf, Literal};
+use crate::boolean_operations::sat::sat::{SatResult, Solver};
+
+// Classes of Cnf with a linear time algorithm, General for everything else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fragment {
+    // At most two literals per clause
+    TwoCnf,
+    // At most one positive literal per clause
+    Horn,
+    General,
+}
+
+/*
+    Strongly connected components of the implication graph of a 2-CNF, whose nodes are
+    literals by code. Iterative Tarjan, so long implication chains can't overflow the stack.
+    Components are numbered as they are completed, which is a reverse topological order:
+    no edge goes from a component to one with a higher number.
+*/
+fn components(graph: &[Vec<usize>]) -> Vec<usize> {
+    let mut index: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut low: Vec<usize> = vec![0; graph.len()];
+    let mut component: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut next_index: usize = 0;
+    let mut next_component: usize = 0;
+
+    for root in 0..graph.len() {
+        if index[root].is_some() {
+            continue;
+        }
+        // Nodes being visited, with the next of their edges to follow
+        let mut calls: Vec<(usize, usize)> = vec![(root, 0)];
+        index[root] = Some(next_index);
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+
+        while let Some((node, edge)) = calls.pop() {
+            if edge < graph[node].len() {
+                calls.push((node, edge + 1));
+                let target: usize = graph[node][edge];
+                match index[target] {
+                    None => {
+                        index[target] = Some(next_index);
+                        low[target] = next_index;
+                        next_index += 1;
+                        stack.push(target);
+                        calls.push((target, 0));
+                    }
+                    Some(target_index) if component[target].is_none() => {
+                        low[node] = low[node].min(target_index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            if Some(low[node]) == index[node] {
+                loop {
+                    let member: usize = stack.pop().unwrap();
+                    component[member] = Some(next_component);
+                    if member == node {
+                        break;
+                    }
+                }
+                next_component += 1;
+            }
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+        }
+    }
+    component.into_iter().map(Option::unwrap).collect()
+}
+
+impl Cnf {
+    pub fn fragment(&self) -> Fragment {
+        if self.clauses().iter().all(|clause| clause.len() <= 2) {
+            Fragment::TwoCnf
+        } else if self.clauses().iter().all(|clause| {
+            clause
+                .literals()
+                .iter()
+                .filter(|literal| !literal.is_negated())
+                .count()
+                <= 1
+        }) {
+            Fragment::Horn
+        } else {
+            Fragment::General
+        }
+    }
+
+    /*
+        2-SAT in linear time, None if a clause has more than two literals.
+        Clause a | b is the implications !a > b and !b > a, and a unit clause a is !a > a.
+        There is no model when a literal and its negation imply each other, that is when
+        they share a component. Otherwise a literal is true when its component comes
+        after the one of its negation in topological order, so no true literal implies a false one.
+    */
+    pub fn two_sat(&self) -> Option<SatResult> {
+        if self.fragment() != Fragment::TwoCnf {
+            return None;
+        }
+        let mut graph: Vec<Vec<usize>> = vec![Vec::new(); 2 * self.num_variables()];
+        for clause in self.clauses() {
+            match clause.literals() {
+                [] => return Some(SatResult::Unsat),
+                [a] => graph[(!*a).code()].push(a.code()),
+                [a, b] => {
+                    graph[(!*a).code()].push(b.code());
+                    graph[(!*b).code()].push(a.code());
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let component: Vec<usize> = components(&graph);
+        let mut values: Vec<bool> = Vec::with_capacity(self.num_variables());
+        for var in 0..self.num_variables() {
+            let positive: usize = component[Literal::positive(var).code()];
+            let negative: usize = component[Literal::negative(var).code()];
+            if positive == negative {
+                return Some(SatResult::Unsat);
+            }
+            values.push(positive < negative);
+        }
+        Some(SatResult::Sat(self.assignment(&values)))
+    }
+
+    /*
+        Horn-SAT in linear time, None if a clause has more than one positive literal.
+        Starting with every variable false, a clause whose negative literals are all
+        made false by true variables forces its positive literal, and there is no model when it
+        has none. Each clause keeps the number of its negative literals still true, so every
+        variable set to true only visits the clauses it appears negated in.
+        The model is the smallest one: only the variables that have to be true are.
+    */
+    pub fn horn_sat(&self) -> Option<SatResult> {
+        let mut heads: Vec<Option<Literal>> = Vec::with_capacity(self.len());
+        for clause in self.clauses() {
+            let mut positive = clause
+                .literals()
+                .iter()
+                .filter(|literal| !literal.is_negated());
+            heads.push(positive.next().copied());
+            if positive.next().is_some() {
+                return None;
+            }
+        }
+
+        let mut remaining: Vec<usize> = vec![0; self.len()];
+        let mut body_of: Vec<Vec<usize>> = vec![Vec::new(); self.num_variables()];
+        for (index, clause) in self.clauses().iter().enumerate() {
+            for literal in clause
+                .literals()
+                .iter()
+                .filter(|literal| literal.is_negated())
+            {
+                remaining[index] += 1;
+                body_of[literal.var()].push(index);
+            }
+        }
+
+        let mut values: Vec<bool> = vec![false; self.num_variables()];
+        let mut queue: Vec<usize> = Vec::new();
+        // Clauses with no negative literal left, facts first
+        let mut forced: Vec<usize> = (0..self.len())
+            .filter(|&index| remaining[index] == 0)
+            .collect();
+        loop {
+            while let Some(index) = forced.pop() {
+                match heads[index] {
+                    None => return Some(SatResult::Unsat),
+                    Some(head) => {
+                        if !values[head.var()] {
+                            values[head.var()] = true;
+                            queue.push(head.var());
+                        }
+                    }
+                }
+            }
+            let var: usize = match queue.pop() {
+                Some(var) => var,
+                None => break,
+            };
+            for &index in &body_of[var] {
+                remaining[index] -= 1;
+                if remaining[index] == 0 {
+                    forced.push(index);
+                }
+            }
+        }
+        Some(SatResult::Sat(self.assignment(&values)))
+    }
+
+    // The solver of the fragment of the Cnf, CDCL when it isn't in one
+    pub fn solve(&self) -> SatResult {
+        match self.fragment() {
+            Fragment::TwoCnf => self.two_sat(),
+            Fragment::Horn => self.horn_sat(),
+            Fragment::General => None,
+        }
+        .unwrap_or_else(|| self.cdcl())
+    }
+}
+
+// Cnf::solve behind the Solver trait
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoSolver;
+
+impl Solver for AutoSolver {
+    fn solve_cnf(&mut self, cnf: &Cnf) -> SatResult {
+        cnf.solve()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean_operations::cnf::Clause;
+    use crate::boolean_operations::sat::test_utils::random_cnf;
+
+    fn check(cnf: &Cnf, result: &SatResult) {
+        assert_eq!(result.is_sat(), cnf.dpll().is_sat(), "{}", cnf);
+        if let SatResult::Sat(model) = result {
+            assert!(cnf.evaluate(model.values()), "{}", cnf);
+        }
+    }
+
+    #[test]
+    fn test_two_sat() {
+        for seed in 1..60 {
+            // Around the threshold of random 2-SAT, about as many clauses as variables
+            let cnf: Cnf = random_cnf(seed * 7919, 30, 20 + seed as usize, 2, 2);
+            assert_eq!(cnf.fragment(), Fragment::TwoCnf);
+            check(&cnf, &cnf.two_sat().unwrap());
+        }
+        let cnf: Cnf = Cnf::from_rpn("AB|A!B|&AB!|&A!B!|&").unwrap();
+        assert_eq!(cnf.two_sat(), Some(SatResult::Unsat));
+        assert_eq!(Cnf::from_rpn("AB|C|").unwrap().two_sat(), None);
+        assert_eq!(
+            Cnf::from_rpn("A0&").unwrap().two_sat(),
+            Some(SatResult::Unsat)
+        );
+
+        // A long chain of implications x0 > x1 > ... > x9999 with x0 and !x9999
+        let mut cnf: Cnf = Cnf::with_variables((0..10_000).map(|i| format!("x{}", i)).collect());
+        for var in 0..9_999 {
+            cnf.add_clause(Clause::new(vec![
+                Literal::negative(var),
+                Literal::positive(var + 1),
+            ]));
+        }
+        cnf.add_clause(Clause::new(vec![Literal::positive(0)]));
+        assert!(cnf
+            .two_sat()
+            .unwrap()
+            .model()
+            .unwrap()
+            .values()
+            .iter()
+            .all(|&value| value));
+        cnf.add_clause(Clause::new(vec![Literal::negative(9_999)]));
+        assert_eq!(cnf.two_sat(), Some(SatResult::Unsat));
+    }
+
+    #[test]
+    fn test_horn() {
+        for seed in 1..60 {
+            let cnf: Cnf = random_cnf(seed * 104_729, 20, 40, 4, 1);
+            assert_ne!(cnf.fragment(), Fragment::General);
+            check(&cnf, &cnf.horn_sat().unwrap());
+        }
+
+        // A, A & B > C, C > D, and E only if D is false
+        let cnf: Cnf = Cnf::from_rpn("AA!B!|C|&C!D|&D!E!|&").unwrap();
+        assert_eq!(cnf.fragment(), Fragment::Horn);
+        // Only what is forced is true
+        let result: SatResult = cnf.horn_sat().unwrap();
+        assert_eq!(
+            result.model().unwrap().values(),
+            &vec![true, false, false, false, false]
+        );
+
+        let cnf: Cnf = Cnf::from_rpn("AA!B|&B!&CD|&").unwrap();
+        assert_eq!(cnf.horn_sat(), None);
+        let cnf: Cnf = Cnf::from_rpn("AA!B|&B!&C!D!|&").unwrap();
+        assert_eq!(cnf.horn_sat(), Some(SatResult::Unsat));
+    }
+
+    #[test]
+    fn test_fallback() {
+        for seed in 1..20 {
+            let cnf: Cnf = random_cnf(seed * 31, 15, 60, 3, 3);
+            let result: SatResult = AutoSolver.solve_cnf(&cnf);
+            check(&cnf, &result);
+        }
+        let cnf: Cnf = Cnf::from_rpn("AB|C|A!B!|&C!&").unwrap();
+        assert_eq!(cnf.fragment(), Fragment::General);
+        assert!(cnf.solve().is_sat());
+        assert_eq!(Cnf::new().fragment(), Fragment::TwoCnf);
+        assert!(Cnf::new().solve().is_sat());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal};
+use crate::boolean_operations::sat::cdcl::{CdclSolver, SolverStats};
+use crate::boolean_operations::sat::sat::SatResult;
+
+/*
+    A CdclSolver kept between queries on the same base formula, so what it learned
+    on one solve speeds up the next ones.
+    Every push opens a scope with its own selector, a variable without a name so it can't
+    clash with a variable of a formula. Clauses added in a scope get the negated selector,
+    and the selectors of the open scopes are assumed on every solve. Popping a scope adds
+    the negated selector as a unit clause, which satisfies its clauses and every clause
+    learned from them for good.
+*/
+#[derive(Debug, Clone)]
+pub struct IncrementalSolver {
+    solver: CdclSolver,
+    scopes: Vec<Literal>,
+    failed: Vec<Literal>,
+}
+
+impl IncrementalSolver {
+    pub fn new(cnf: &Cnf) -> Self {
+        IncrementalSolver {
+            solver: CdclSolver::new(cnf),
+            scopes: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    pub fn stats(&self) -> &SolverStats {
+        self.solver.stats()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<usize> {
+        self.solver.variable(name)
+    }
+
+    pub fn add_variable(&mut self, name: &str) -> usize {
+        self.solver.add_variable(name)
+    }
+
+    // Number of scopes pushed and not popped yet
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    // The clause is removed again when the current scope is popped
+    pub fn add_clause(&mut self, clause: &Clause) {
+        let mut clause: Clause = clause.clone();
+        if let Some(&selector) = self.scopes.last() {
+            clause.push(!selector);
+        }
+        self.solver.add_clause(&clause);
+    }
+
+    // Adds a clause of (variable, negated) literals, with the variables it is missing
+    pub fn add_named_clause(&mut self, literals: &[(String, bool)]) {
+        let clause: Clause = literals
+            .iter()
+            .map(|(name, negated)| Literal::new(self.add_variable(name), *negated))
+            .collect();
+        self.add_clause(&clause);
+    }
+
+    pub fn push(&mut self) {
+        let var: usize = self.solver.new_variable();
+        self.scopes.push(Literal::positive(var));
+    }
+
+    // Removes every clause added since the matching push
+    pub fn pop(&mut self) {
+        let selector: Literal = self.scopes.pop().expect("pop without a matching push");
+        self.solver.add_clause(&Clause::new(vec![!selector]));
+    }
+
+    pub fn solve(&mut self) -> SatResult {
+        self.solve_with_assumptions(&[])
+    }
+
+    /*
+        Solves with the clauses of the open scopes and every assumption true.
+        The model only has the variables of the formula, the solver leaves the selectors out.
+    */
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> SatResult {
+        let mut all: Vec<Literal> = self.scopes.clone();
+        all.extend_from_slice(assumptions);
+        let result: SatResult = self.solver.solve_with_assumptions(&all);
+        self.failed = self
+            .solver
+            .failed_assumptions()
+            .iter()
+            .copied()
+            .filter(|literal| !self.scopes.contains(literal))
+            .collect();
+        result
+    }
+
+    /*
+        After an unsatisfiable solve, a subset of its assumptions that is already
+        unsatisfiable with the clauses. Empty when the clauses alone are.
+    */
+    pub fn failed_assumptions(&self) -> &[Literal] {
+        &self.failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aux::Assignment;
+
+    #[test]
+    fn test_assumptions() {
+        // A > B, B > C, C > D
+        let cnf: Cnf = Cnf::from_rpn("A!B|B!C|&C!D|&").unwrap();
+        let mut solver: IncrementalSolver = IncrementalSolver::new(&cnf);
+        let (a, b, c, d) = (0, 1, 2, 3);
+
+        let result: SatResult = solver.solve_with_assumptions(&[Literal::positive(a)]);
+        assert_eq!(result.model().unwrap().values(), &vec![true; 4]);
+
+        let assumptions: [Literal; 3] = [
+            Literal::positive(c),
+            Literal::positive(a),
+            Literal::negative(d),
+        ];
+        assert_eq!(
+            solver.solve_with_assumptions(&assumptions),
+            SatResult::Unsat
+        );
+        let mut failed: Vec<Literal> = solver.failed_assumptions().to_vec();
+        failed.sort();
+        // C and !D are enough, A isn't needed
+        assert_eq!(failed, [Literal::positive(c), Literal::negative(d)]);
+
+        // The assumptions don't stay
+        assert!(solver
+            .solve_with_assumptions(&[Literal::negative(b)])
+            .is_sat());
+        assert!(solver.failed_assumptions().is_empty());
+
+        // Contradictory assumptions fail together
+        let assumptions: [Literal; 2] = [Literal::positive(b), Literal::negative(b)];
+        assert_eq!(
+            solver.solve_with_assumptions(&assumptions),
+            SatResult::Unsat
+        );
+        assert_eq!(solver.failed_assumptions().len(), 2);
+
+        // A clause added after a solve
+        solver.add_named_clause(&[("A".to_string(), false)]);
+        solver.add_named_clause(&[("D".to_string(), true), ("E".to_string(), false)]);
+        let result: SatResult = solver.solve();
+        assert_eq!(result.model().unwrap().get("E"), Some(&true));
+        assert_eq!(
+            solver.solve_with_assumptions(&[Literal::negative(c)]),
+            SatResult::Unsat
+        );
+        assert_eq!(solver.failed_assumptions(), [Literal::negative(c)]);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let cnf: Cnf = Cnf::from_rpn("AB|").unwrap();
+        let mut solver: IncrementalSolver = IncrementalSolver::new(&cnf);
+        solver.push();
+        solver.add_clause(&Clause::new(vec![Literal::negative(0)]));
+        solver.push();
+        solver.add_clause(&Clause::new(vec![Literal::negative(1)]));
+        assert_eq!(solver.depth(), 2);
+        assert_eq!(solver.solve(), SatResult::Unsat);
+        // Only the assumptions given by the caller are reported
+        assert!(solver.failed_assumptions().is_empty());
+
+        solver.pop();
+        let result: SatResult = solver.solve();
+        let model: &Assignment<bool> = result.model().unwrap();
+        assert_eq!(model.len(), 2);
+        assert_eq!(
+            (model.get("A"), model.get("B")),
+            (Some(&false), Some(&true))
+        );
+
+        solver.pop();
+        assert!(solver
+            .solve_with_assumptions(&[Literal::positive(0), Literal::negative(1)])
+            .is_sat());
+
+        // Scopes can be opened again after popping
+        solver.push();
+        solver.add_named_clause(&[("A".to_string(), true)]);
+        solver.add_named_clause(&[("A".to_string(), false)]);
+        assert_eq!(solver.solve(), SatResult::Unsat);
+        solver.pop();
+        assert!(solver.solve().is_sat());
+    }
+
+    #[test]
+    fn test_selector_names() {
+        // Variables named like the selectors used to be stay variables of the formula
+        let cnf: Cnf = Cnf::from_named_clauses(&[
+            vec![("#scope0".to_string(), false)],
+            vec![("#x".to_string(), true), ("A".to_string(), false)],
+        ]);
+        let mut solver: IncrementalSolver = IncrementalSolver::new(&cnf);
+        solver.push();
+        solver.add_named_clause(&[("#scope0".to_string(), true)]);
+        assert_eq!(solver.solve(), SatResult::Unsat);
+        solver.pop();
+
+        let result: SatResult = solver.solve();
+        let model: &Assignment<bool> = result.model().unwrap();
+        assert_eq!(model.len(), 3);
+        assert_eq!(model.get("#scope0"), Some(&true));
+        assert!(model.contains("#x"));
+    }
+
+    #[test]
+    fn test_many_queries() {
+        // x0 = x1 = ... = x19 as a chain of equivalences between neighbours
+        let mut clauses: Vec<Vec<(String, bool)>> = Vec::new();
+        for i in 0..19 {
+            let (x, y) = (format!("x{:02}", i), format!("x{:02}", i + 1));
+            clauses.push(vec![(x.clone(), true), (y.clone(), false)]);
+            clauses.push(vec![(x, false), (y, true)]);
+        }
+        let cnf: Cnf = Cnf::from_named_clauses(&clauses);
+        let mut solver: IncrementalSolver = IncrementalSolver::new(&cnf);
+        for i in 0..20 {
+            for j in 0..20 {
+                let same: [Literal; 2] = [Literal::positive(i), Literal::positive(j)];
+                assert!(solver.solve_with_assumptions(&same).is_sat());
+                let different: [Literal; 2] = [Literal::positive(i), Literal::negative(j)];
+                assert_eq!(solver.solve_with_assumptions(&different), SatResult::Unsat);
+                assert_eq!(solver.failed_assumptions().len(), 2, "{} {}", i, j);
+            }
+        }
+        assert_eq!(solver.stats().decisions, 0);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal};
+use crate::boolean_operations::sat::sat::{SatResult, Solver};
+
+// Flips allowed per try and restarts from a new random assignment, unless changed
+const DEFAULT_MAX_FLIPS: u64 = 100_000;
+const DEFAULT_MAX_RESTARTS: u64 = 10;
+
+// How the variable to flip is chosen among those of an unsatisfied clause
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Heuristic {
+    /*
+        A variable whose flip breaks no satisfied clause if there is one, otherwise
+        a random one with probability noise and one that breaks the fewest clauses if not.
+    */
+    WalkSat { noise: f64 },
+    // Any variable, with probability proportional to (1 + breaks)^-cb
+    ProbSat { cb: f64 },
+}
+
+/*
+    Xorshift64* generator, so a run only depends on its seed.
+    The seed goes through splitmix64 first: the state can't be 0 and
+    seeds next to each other don't start with similar numbers.
+*/
+#[derive(Debug, Clone)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        let mut z: u64 = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng {
+            state: (z ^ (z >> 31)) | 1,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/*
+    A complete assignment with the number of true literals of every clause,
+    and the unsatisfied clauses in a list with the position of each in it,
+    so a flip only updates the clauses of the flipped variable.
+*/
+struct Search {
+    clauses: Vec<Clause>,
+    // Clauses of each literal, by code
+    occurrences: Vec<Vec<usize>>,
+    values: Vec<bool>,
+    true_count: Vec<usize>,
+    unsat: Vec<usize>,
+    unsat_position: Vec<Option<usize>>,
+}
+
+impl Search {
+    // Tautologies are left out, they are true whatever the search does
+    fn new(cnf: &Cnf) -> Self {
+        let clauses: Vec<Clause> = cnf
+            .clauses()
+            .iter()
+            .map(|clause| {
+                let mut clause: Clause = clause.clone();
+                clause.normalize();
+                clause
+            })
+            .filter(|clause| !clause.is_tautology())
+            .collect();
+        let mut occurrences: Vec<Vec<usize>> = vec![Vec::new(); 2 * cnf.num_variables()];
+        for (index, clause) in clauses.iter().enumerate() {
+            for literal in clause.literals() {
+                occurrences[literal.code()].push(index);
+            }
+        }
+        Search {
+            true_count: vec![0; clauses.len()],
+            unsat_position: vec![None; clauses.len()],
+            clauses,
+            occurrences,
+            values: vec![false; cnf.num_variables()],
+            unsat: Vec::new(),
+        }
+    }
+
+    fn randomize(&mut self, rng: &mut Rng) {
+        for value in self.values.iter_mut() {
+            *value = rng.next() & 1 == 1;
+        }
+        self.unsat.clear();
+        for index in 0..self.clauses.len() {
+            self.true_count[index] = self.clauses[index]
+                .literals()
+                .iter()
+                .filter(|literal| literal.value(self.values[literal.var()]))
+                .count();
+            self.unsat_position[index] = None;
+            if self.true_count[index] == 0 {
+                self.unsat_position[index] = Some(self.unsat.len());
+                self.unsat.push(index);
+            }
+        }
+    }
+
+    // The literal of var that is true now
+    fn true_literal(&self, var: usize) -> Literal {
+        Literal::new(var, !self.values[var])
+    }
+
+    // Satisfied clauses that flipping var would make false
+    fn breaks(&self, var: usize) -> usize {
+        self.occurrences[self.true_literal(var).code()]
+            .iter()
+            .filter(|&&index| self.true_count[index] == 1)
+            .count()
+    }
+
+    fn flip(&mut self, var: usize) {
+        let falsified: Literal = self.true_literal(var);
+        self.values[var] = !self.values[var];
+        for &index in &self.occurrences[falsified.code()] {
+            self.true_count[index] -= 1;
+            if self.true_count[index] == 0 {
+                self.unsat_position[index] = Some(self.unsat.len());
+                self.unsat.push(index);
+            }
+        }
+        for &index in &self.occurrences[(!falsified).code()] {
+            self.true_count[index] += 1;
+            if self.true_count[index] == 1 {
+                // The last unsatisfied clause takes its place in the list
+                let position: usize = self.unsat_position[index].take().unwrap();
+                let last: usize = self.unsat.pop().unwrap();
+                if last != index {
+                    self.unsat[position] = last;
+                    self.unsat_position[last] = Some(position);
+                }
+            }
+        }
+    }
+}
+
+/*
+    Stochastic local search: starts from a random assignment and flips variables
+    of unsatisfied clauses until none is left, restarting after max_flips flips.
+    It can find models of large satisfiable formulas quickly but can't show that
+    there is none, so it answers Unknown once every try failed.
+    The generator is kept between calls, the same seed gives the same sequence of answers.
+*/
+#[derive(Debug, Clone)]
+pub struct LocalSearch {
+    pub heuristic: Heuristic,
+    pub max_flips: u64,
+    pub max_restarts: u64,
+    rng: Rng,
+    flips: u64,
+}
+
+impl LocalSearch {
+    pub fn new(heuristic: Heuristic, seed: u64) -> Self {
+        LocalSearch {
+            heuristic,
+            max_flips: DEFAULT_MAX_FLIPS,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            rng: Rng::new(seed),
+            flips: 0,
+        }
+    }
+
+    // The noise found best for random 3-SAT
+    pub fn walksat(seed: u64) -> Self {
+        LocalSearch::new(Heuristic::WalkSat { noise: 0.567 }, seed)
+    }
+
+    // The polynomial break value found best for random 3-SAT
+    pub fn probsat(seed: u64) -> Self {
+        LocalSearch::new(Heuristic::ProbSat { cb: 2.38 }, seed)
+    }
+
+    // Flips done by the last solve
+    pub fn flips(&self) -> u64 {
+        self.flips
+    }
+
+    // Unsat only for a Cnf with the empty clause, Unknown when no model was found
+    pub fn solve(&mut self, cnf: &Cnf) -> SatResult {
+        self.flips = 0;
+        if cnf.clauses().iter().any(|clause| clause.is_empty()) {
+            return SatResult::Unsat;
+        }
+        let mut search: Search = Search::new(cnf);
+        for _ in 0..=self.max_restarts {
+            search.randomize(&mut self.rng);
+            for _ in 0..self.max_flips {
+                if search.unsat.is_empty() {
+                    break;
+                }
+                let index: usize = search.unsat[self.rng.below(search.unsat.len())];
+                let var: usize = self.pick(&search, &search.clauses[index]);
+                search.flip(var);
+                self.flips += 1;
+            }
+            if search.unsat.is_empty() {
+                return SatResult::Sat(cnf.assignment(&search.values));
+            }
+        }
+        SatResult::Unknown
+    }
+
+    // Variable of the unsatisfied clause to flip
+    fn pick(&mut self, search: &Search, clause: &Clause) -> usize {
+        let vars: Vec<usize> = clause
+            .literals()
+            .iter()
+            .map(|literal| literal.var())
+            .collect();
+        let breaks: Vec<usize> = vars.iter().map(|&var| search.breaks(var)).collect();
+        match self.heuristic {
+            Heuristic::WalkSat { noise } => {
+                let fewest: usize = *breaks.iter().min().unwrap();
+                if fewest > 0 && self.rng.unit() < noise {
+                    return vars[self.rng.below(vars.len())];
+                }
+                let best: Vec<usize> = (0..vars.len())
+                    .filter(|&i| breaks[i] == fewest)
+                    .map(|i| vars[i])
+                    .collect();
+                best[self.rng.below(best.len())]
+            }
+            Heuristic::ProbSat { cb } => {
+                let weights: Vec<f64> = breaks
+                    .iter()
+                    .map(|&count| (1.0 + count as f64).powf(-cb))
+                    .collect();
+                let mut target: f64 = self.rng.unit() * weights.iter().sum::<f64>();
+                for (i, weight) in weights.iter().enumerate() {
+                    if target < *weight {
+                        return vars[i];
+                    }
+                    target -= weight;
+                }
+                // Only reached through rounding
+                *vars.last().unwrap()
+            }
+        }
+    }
+}
+
+impl Solver for LocalSearch {
+    fn solve_cnf(&mut self, cnf: &Cnf) -> SatResult {
+        self.solve(cnf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean_operations::sat::cdcl::CdclSolver;
+    use crate::boolean_operations::sat::dpll::DpllSolver;
+    use crate::boolean_operations::sat::test_utils::planted_ksat;
+
+    #[test]
+    fn test_finds_models() {
+        for seed in 0..5 {
+            let cnf: Cnf = planted_ksat(seed, 150, 600, 3);
+            for mut search in [LocalSearch::walksat(seed), LocalSearch::probsat(seed)] {
+                let result: SatResult = search.solve(&cnf);
+                assert!(
+                    cnf.evaluate(result.model().unwrap().values()),
+                    "{:?}",
+                    search.heuristic
+                );
+                assert!(search.flips() <= search.max_flips * (search.max_restarts + 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        let cnf: Cnf = planted_ksat(42, 100, 400, 3);
+        let mut first: LocalSearch = LocalSearch::probsat(7);
+        let mut second: LocalSearch = LocalSearch::probsat(7);
+        assert_eq!(first.solve(&cnf), second.solve(&cnf));
+        assert_eq!(first.flips(), second.flips());
+        assert_eq!(first.solve(&cnf), second.solve(&cnf));
+    }
+
+    #[test]
+    fn test_unknown() {
+        // Every clause of two variables, no model to find
+        let cnf: Cnf = Cnf::from_rpn("AB|A!B|&AB!|&A!B!|&").unwrap();
+        let mut search: LocalSearch = LocalSearch::walksat(1);
+        search.max_flips = 50;
+        search.max_restarts = 3;
+        assert_eq!(search.solve(&cnf), SatResult::Unknown);
+        assert_eq!(search.flips(), 200);
+
+        assert_eq!(
+            search.solve(&Cnf::from_rpn("A0&").unwrap()),
+            SatResult::Unsat
+        );
+        assert!(search.solve(&Cnf::from_rpn("AA!|").unwrap()).is_sat());
+        assert!(search.solve(&Cnf::new()).is_sat());
+    }
+
+    #[test]
+    fn test_solver_trait() {
+        let mut solvers: Vec<Box<dyn Solver>> = vec![
+            Box::new(DpllSolver),
+            Box::new(CdclSolver::default()),
+            Box::new(LocalSearch::probsat(3)),
+        ];
+        let satisfiable: Cnf = planted_ksat(9, 40, 160, 3);
+        let unsatisfiable: Cnf = Cnf::from_rpn("AB|A!B|&AB!|&A!B!|&").unwrap();
+        for solver in solvers.iter_mut() {
+            let result: SatResult = solver.solve_cnf(&satisfiable);
+            assert!(satisfiable.evaluate(result.model().unwrap().values()));
+            assert!(!solver.solve_cnf(&unsatisfiable).is_sat());
+        }
+        assert_eq!(solvers[0].solve_cnf(&unsatisfiable), SatResult::Unsat);
+        assert_eq!(solvers[1].solve_cnf(&unsatisfiable), SatResult::Unsat);
+    }
+}
@@ -0,0 +1,470 @@
+use crate::aux::Assignment;
+use crate::boolean_operations::cnf::dimacs::{dimacs_literal, variable_names};
+use crate::boolean_operations::cnf::{Clause, Cnf, DimacsError, Literal};
+use crate::boolean_operations::sat::cdcl::CdclSolver;
+use crate::boolean_operations::sat::sat::SatResult;
+use std::collections::HashMap;
+
+/*
+    Weighted partial MaxSAT instance: hard clauses that must hold, kept as a Cnf
+    with the variables, and soft clauses that each cost their weight when false.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wcnf {
+    hard: Cnf,
+    soft: Vec<(Clause, u64)>,
+}
+
+// Outcome of a MaxSAT solver, Unsat when the hard clauses alone have no model
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaxSatResult {
+    Optimum { cost: u64, model: Assignment<bool> },
+    Unsat,
+}
+
+impl MaxSatResult {
+    pub fn cost(&self) -> Option<u64> {
+        match self {
+            MaxSatResult::Optimum { cost, .. } => Some(*cost),
+            MaxSatResult::Unsat => None,
+        }
+    }
+
+    pub fn model(&self) -> Option<&Assignment<bool>> {
+        match self {
+            MaxSatResult::Optimum { model, .. } => Some(model),
+            MaxSatResult::Unsat => None,
+        }
+    }
+}
+
+// A soft clause of the solver, with the relaxation variables it got so far
+struct Soft {
+    clause: Clause,
+    weight: u64,
+    // The clause is only enforced while the negation of its selector is assumed
+    selector: Literal,
+}
+
+impl Wcnf {
+    // Every clause of the Cnf is hard
+    pub fn new(hard: Cnf) -> Self {
+        Wcnf {
+            hard,
+            soft: Vec::new(),
+        }
+    }
+
+    pub fn with_variables(variables: Vec<String>) -> Self {
+        Wcnf::new(Cnf::with_variables(variables))
+    }
+
+    pub fn variables(&self) -> &[String] {
+        self.hard.variables()
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.hard.num_variables()
+    }
+
+    pub fn hard(&self) -> &Cnf {
+        &self.hard
+    }
+
+    pub fn soft(&self) -> &[(Clause, u64)] {
+        &self.soft
+    }
+
+    pub fn add_hard(&mut self, clause: Clause) {
+        self.hard.add_clause(clause);
+    }
+
+    pub fn add_soft(&mut self, clause: Clause, weight: u64) {
+        assert!(
+            clause
+                .literals()
+                .iter()
+                .all(|literal| literal.var() < self.num_variables()),
+            "Literal of a variable the Wcnf doesn't have"
+        );
+        self.soft.push((clause, weight));
+    }
+
+    /*
+        Weight of the soft clauses an assignment falsifies, None if it falsifies a hard one.
+        A weight that doesn't fit in u64 is u64::MAX.
+    */
+    pub fn cost(&self, values: &[bool]) -> Option<u64> {
+        if !self.hard.evaluate(values) {
+            return None;
+        }
+        Some(
+            self.soft
+                .iter()
+                .filter(|(clause, _)| !clause.evaluate(values))
+                .fold(0, |cost: u64, (_, weight)| cost.saturating_add(*weight)),
+        )
+    }
+
+    /*
+        Core-guided search for an assignment of minimal cost (WPM1).
+        Each soft clause gets a selector variable and its negation is assumed on every solve.
+        While there is no model, the failed assumptions give a core of soft clauses that
+        can't all hold, and at least the smallest weight w in it has to be paid: w goes
+        into the cost, every clause of the core is split into a copy of weight w that
+        gets a new relaxation variable and the rest of its weight, and at most one of the
+        new relaxation variables can be true. The first model is then an optimal one.
+    */
+    pub fn maxsat(&self) -> MaxSatResult {
+        let mut solver: CdclSolver = CdclSolver::new(&self.hard);
+        let mut softs: Vec<Soft> = Vec::new();
+        let new_soft = |solver: &mut CdclSolver, mut clause: Clause, weight: u64| -> Soft {
+            let selector: Literal = Literal::positive(solver.new_variable());
+            let original: Clause = clause.clone();
+            clause.push(selector);
+            solver.add_clause(&clause);
+            Soft {
+                clause: original,
+                weight,
+                selector,
+            }
+        };
+        for (clause, weight) in self.soft.iter().filter(|(_, weight)| *weight > 0) {
+            softs.push(new_soft(&mut solver, clause.clone(), *weight));
+        }
+
+        loop {
+            let assumptions: Vec<Literal> = softs.iter().map(|soft| !soft.selector).collect();
+            if let SatResult::Sat(model) = solver.solve_with_assumptions(&assumptions) {
+                let values: Vec<bool> = model.values()[..self.num_variables()].to_vec();
+                return MaxSatResult::Optimum {
+                    cost: self.cost(&values).unwrap(),
+                    model: self.hard.assignment(&values),
+                };
+            }
+            let core: Vec<usize> = (0..softs.len())
+                .filter(|&i| solver.failed_assumptions().contains(&!softs[i].selector))
+                .collect();
+            if core.is_empty() {
+                return MaxSatResult::Unsat;
+            }
+
+            let weight: u64 = core.iter().map(|&i| softs[i].weight).min().unwrap();
+            let mut relaxed: Vec<Literal> = Vec::new();
+            for &i in &core {
+                let relaxation: Literal = Literal::positive(solver.new_variable());
+                relaxed.push(relaxation);
+                let mut clause: Clause = softs[i].clause.clone();
+                clause.push(relaxation);
+                let soft: Soft = new_soft(&mut solver, clause, weight);
+                softs.push(soft);
+                softs[i].weight -= weight;
+            }
+            for (k, &first) in relaxed.iter().enumerate() {
+                for &second in &relaxed[k + 1..] {
+                    solver.add_clause(&Clause::new(vec![!first, !second]));
+                }
+            }
+            // Clauses whose whole weight went to their copy are no longer enforced
+            softs.retain(|soft| soft.weight > 0);
+        }
+    }
+
+    /*
+        Writes the instance in the 'p wcnf' format, with the variables named as in to_dimacs.
+        Hard clauses get the top weight, one more than the sum of the soft ones.
+        The top weight stops at u64::MAX, a soft clause of that weight is read back as hard.
+    */
+    pub fn to_wcnf(&self) -> String {
+        let top: u64 = self
+            .soft
+            .iter()
+            .fold(1, |top: u64, (_, weight)| top.saturating_add(*weight));
+        let mut wcnf: String = String::new();
+        for (var, name) in self.variables().iter().enumerate() {
+            wcnf.push_str(&format!("c {} {}\n", var + 1, name));
+        }
+        wcnf.push_str(&format!(
+            "p wcnf {} {} {}\n",
+            self.num_variables(),
+            self.hard.len() + self.soft.len(),
+            top
+        ));
+        let clauses = self
+            .hard
+            .clauses()
+            .iter()
+            .map(|clause| (clause, top))
+            .chain(self.soft.iter().map(|(clause, weight)| (clause, *weight)));
+        for (clause, weight) in clauses {
+            wcnf.push_str(&format!("{} ", weight));
+            for literal in clause.literals() {
+                wcnf.push_str(&format!("{} ", dimacs_literal(*literal)));
+            }
+            wcnf.push_str("0\n");
+        }
+        wcnf
+    }
+
+    /*
+        Reads a WCNF file: either with a 'p wcnf <variables> <clauses> <top>' header
+        and a weight before every clause, those of at least top being hard, or in the
+        newer format without header where hard clauses start with h.
+        One clause per line, names come from comments as in Cnf::from_dimacs.
+    */
+    pub fn from_wcnf(input: &str) -> Result<Self, DimacsError> {
+        let mut header: Option<(usize, usize, Option<u64>)> = None;
+        let mut names: HashMap<usize, (String, usize)> = HashMap::new();
+        let mut hard: Vec<Clause> = Vec::new();
+        let mut soft: Vec<(Clause, u64)> = Vec::new();
+        let mut max_var: usize = 0;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number: usize = index + 1;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let invalid = |token: &str| DimacsError::InvalidToken {
+                line: line_number,
+                snippet: token.to_string(),
+            };
+            match tokens.first() {
+                None => continue,
+                Some(&"c") => {
+                    if let [_, var, name] = tokens[..] {
+                        if let Ok(var) = var.parse::<usize>() {
+                            names.insert(var, (name.to_string(), line_number));
+                        }
+                    }
+                    continue;
+                }
+                Some(&"p") => {
+                    if header.is_some() || !hard.is_empty() || !soft.is_empty() {
+                        return Err(DimacsError::InvalidHeader { line: line_number });
+                    }
+                    let numbers: Vec<Option<u64>> = tokens
+                        .iter()
+                        .skip(2)
+                        .map(|token| token.parse().ok())
+                        .collect();
+                    header = match (tokens.get(1), &numbers[..]) {
+                        (Some(&"wcnf"), [Some(num_variables), Some(num_clauses)]) => {
+                            Some((*num_variables as usize, *num_clauses as usize, None))
+                        }
+                        (Some(&"wcnf"), [Some(num_variables), Some(num_clauses), Some(top)]) => {
+                            Some((*num_variables as usize, *num_clauses as usize, Some(*top)))
+                        }
+                        _ => return Err(DimacsError::InvalidHeader { line: line_number }),
+                    };
+                    continue;
+                }
+                _ => {}
+            }
+
+            let weight: Option<u64> = match tokens[0] {
+                "h" if header.is_none() => None,
+                token => {
+                    let weight: u64 = token.parse().map_err(|_| invalid(token))?;
+                    match header {
+                        Some((_, _, Some(top))) if weight >= top => None,
+                        _ => Some(weight),
+                    }
+                }
+            };
+            let mut clause: Clause = Clause::default();
+            let mut terminated: bool = false;
+            for &token in &tokens[1..] {
+                let value: i64 = token.parse().map_err(|_| invalid(token))?;
+                if value == 0 {
+                    terminated = true;
+                    break;
+                }
+                let var: usize = value.unsigned_abs() as usize;
+                if let Some((num_variables, _, _)) = header {
+                    if var > num_variables {
+                        return Err(DimacsError::VariableOutOfRange {
+                            line: line_number,
+                            var,
+                            num_variables,
+                        });
+                    }
+                }
+                max_var = max_var.max(var);
+                clause.push(Literal::new(var - 1, value < 0));
+            }
+            if !terminated {
+                return Err(invalid("missing 0"));
+            }
+            match weight {
+                None => hard.push(clause),
+                Some(weight) => soft.push((clause, weight)),
+            }
+        }
+
+        let num_variables: usize = match header {
+            Some((num_variables, num_clauses, _)) => {
+                if hard.len() + soft.len() != num_clauses {
+                    return Err(DimacsError::ClauseCount {
+                        expected: num_clauses,
+                        found: hard.len() + soft.len(),
+                    });
+                }
+                num_variables
+            }
+            None => max_var,
+        };
+        let mut wcnf: Wcnf = Wcnf::with_variables(variable_names(&names, num_variables)?);
+        for clause in hard {
+            wcnf.add_hard(clause);
+        }
+        for (clause, weight) in soft {
+            wcnf.add_soft(clause, weight);
+        }
+        Ok(wcnf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean_operations::sat::test_utils::{variables, RandomClauses};
+
+    // Cheapest assignment by trying all of them
+    fn brute_force(wcnf: &Wcnf) -> Option<u64> {
+        (0..1u64 << wcnf.num_variables())
+            .filter_map(|row| {
+                let values: Vec<bool> = (0..wcnf.num_variables())
+                    .map(|var| row >> var & 1 == 1)
+                    .collect();
+                wcnf.cost(&values)
+            })
+            .min()
+    }
+
+    fn random_wcnf(seed: u64, num_variables: usize, num_hard: usize, num_soft: usize) -> Wcnf {
+        let mut random: RandomClauses = RandomClauses::new(seed, num_variables);
+        let mut wcnf: Wcnf = Wcnf::with_variables(variables(num_variables));
+        for _ in 0..num_hard {
+            wcnf.add_hard(random.clause(3, 3));
+        }
+        for _ in 0..num_soft {
+            let clause: Clause = random.clause(3, 3);
+            wcnf.add_soft(clause, 1 + random.next() % 9);
+        }
+        wcnf
+    }
+
+    #[test]
+    fn test_optimum() {
+        for seed in 1..80 {
+            let wcnf: Wcnf = random_wcnf(seed * 7919, 8, 6, 14);
+            let result: MaxSatResult = wcnf.maxsat();
+            assert_eq!(result.cost(), brute_force(&wcnf), "{}", wcnf.to_wcnf());
+            if let MaxSatResult::Optimum { cost, model } = result {
+                assert_eq!(wcnf.cost(model.values()), Some(cost));
+                assert_eq!(model.len(), 8);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small() {
+        // A and B can't both hold, A is worth more, and !A | C and !C cost 1 each
+        let mut wcnf: Wcnf = Wcnf::with_variables(vec!["A".into(), "B".into(), "C".into()]);
+        wcnf.add_hard(Clause::new(vec![
+            Literal::negative(0),
+            Literal::negative(1),
+        ]));
+        wcnf.add_soft(Clause::new(vec![Literal::positive(0)]), 5);
+        wcnf.add_soft(Clause::new(vec![Literal::positive(1)]), 3);
+        wcnf.add_soft(
+            Clause::new(vec![Literal::negative(0), Literal::positive(2)]),
+            1,
+        );
+        wcnf.add_soft(Clause::new(vec![Literal::negative(2)]), 1);
+        let result: MaxSatResult = wcnf.maxsat();
+        assert_eq!(result.cost(), Some(4));
+        assert_eq!(result.model().unwrap().values()[..2], [true, false]);
+
+        // No soft clause, or only empty ones
+        assert_eq!(
+            Wcnf::with_variables(vec!["A".into()]).maxsat().cost(),
+            Some(0)
+        );
+        let mut wcnf: Wcnf = Wcnf::with_variables(Vec::new());
+        wcnf.add_soft(Clause::default(), 2);
+        wcnf.add_soft(Clause::default(), 0);
+        assert_eq!(wcnf.maxsat().cost(), Some(2));
+
+        let wcnf: Wcnf = Wcnf::new(Cnf::from_rpn("AA!&").unwrap());
+        assert_eq!(wcnf.maxsat(), MaxSatResult::Unsat);
+
+        // A variable can't be mistaken for a selector because of its name
+        let wcnf: Wcnf =
+            Wcnf::from_wcnf("c 1 A\nc 2 #soft0\np wcnf 2 2 10\n10 2 0\n5 1 0\n").unwrap();
+        let result: MaxSatResult = wcnf.maxsat();
+        assert_eq!(result.cost(), Some(0));
+        assert_eq!(result.model().unwrap().values(), &vec![true, true]);
+    }
+
+    #[test]
+    fn test_wcnf_format() {
+        let wcnf: Wcnf = random_wcnf(12345, 6, 3, 5);
+        let written: String = wcnf.to_wcnf();
+        assert!(written.contains("p wcnf 6 8 "));
+        assert_eq!(Wcnf::from_wcnf(&written).unwrap(), wcnf);
+
+        let classic: Wcnf =
+            Wcnf::from_wcnf("c 1 A\np wcnf 2 3 10\n10 1 2 0\n3 -1 0\n4 -2 0\n").unwrap();
+        let newer: Wcnf = Wcnf::from_wcnf("c 1 A\nh 1 2 0\n3 -1 0\n4 -2 0\n").unwrap();
+        assert_eq!(classic, newer);
+        assert_eq!(classic.variables(), ["A", "x2"]);
+        assert_eq!(classic.hard().len(), 1);
+        assert_eq!(classic.maxsat().cost(), Some(3));
+
+        assert_eq!(
+            Wcnf::from_wcnf("p wcnf 2 1 10\n3 1 3 0\n"),
+            Err(DimacsError::VariableOutOfRange {
+                line: 2,
+                var: 3,
+                num_variables: 2
+            })
+        );
+        assert_eq!(
+            Wcnf::from_wcnf("p wcnf 2 2 10\n3 1 0\n"),
+            Err(DimacsError::ClauseCount {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            Wcnf::from_wcnf("x 1 0\n"),
+            Err(DimacsError::InvalidToken {
+                line: 1,
+                snippet: "x".to_string()
+            })
+        );
+        assert_eq!(
+            Wcnf::from_wcnf("p cnf 2 1\n"),
+            Err(DimacsError::InvalidHeader { line: 1 })
+        );
+        assert_eq!(
+            Wcnf::from_wcnf("h 1 2 0\n3 -1\n"),
+            Err(DimacsError::InvalidToken {
+                line: 2,
+                snippet: "missing 0".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_large_weights() {
+        let mut wcnf: Wcnf = Wcnf::new(Cnf::from_rpn("AB|").unwrap());
+        wcnf.add_soft(Clause::new(vec![Literal::negative(0)]), u64::MAX - 1);
+        wcnf.add_soft(Clause::new(vec![Literal::negative(1)]), u64::MAX - 1);
+        // The sums stop at u64::MAX instead of overflowing
+        assert_eq!(wcnf.cost(&[true, true]), Some(u64::MAX));
+        let written: String = wcnf.to_wcnf();
+        assert!(written.contains(&format!("p wcnf 2 3 {}", u64::MAX)));
+        assert_eq!(Wcnf::from_wcnf(&written).unwrap(), wcnf);
+        assert_eq!(wcnf.maxsat().cost(), Some(u64::MAX - 1));
+    }
+}
@@ -1,3 +1,23 @@
+pub mod all_sat;
+pub mod cdcl;
+pub mod dpll;
+pub mod drat;
+pub mod fragments;
+pub mod incremental;
+pub mod local_search;
+pub mod maxsat;
+pub mod model_count;
 pub mod sat;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub mod unsat_core;
 
-pub use sat::run_sat_truth_table;
\ No newline at end of file
+pub use all_sat::Models;
+pub use cdcl::{CdclSolver, SolverStats};
+pub use dpll::DpllSolver;
+pub use drat::{DratProof, ProofError, ProofStep};
+pub use fragments::{AutoSolver, Fragment};
+pub use incremental::IncrementalSolver;
+pub use local_search::{Heuristic, LocalSearch};
+pub use maxsat::{MaxSatResult, Wcnf};
+pub use sat::{format_model, run_sat_truth_table, SatResult, Solver};
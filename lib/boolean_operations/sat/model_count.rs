@@ -0,0 +1,420 @@
+use crate::aux::big_uint::BigUint;
+use crate::aux::{ExpressionEvaluator, ParseError};
+use crate::boolean_operations::cnf::{Cnf, Literal, TseitinCnf};
+use crate::boolean_operations::BooleanOperations;
+use std::collections::{HashMap, HashSet};
+
+// What a count is made of: BigUint for the number of models, f64 for weighted counts
+trait CountValue: Clone {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn sum(&self, other: &Self) -> Self;
+    fn product(&self, other: &Self) -> Self;
+}
+
+impl CountValue for BigUint {
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+
+    fn one() -> Self {
+        BigUint::one()
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        self * other
+    }
+}
+
+impl CountValue for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        self * other
+    }
+}
+
+type Clauses = Vec<Vec<Literal>>;
+
+/*
+    Exact (weighted) model counting by search over the clauses.
+    After unit propagation the clauses left are split into components that share no variable,
+    whose counts multiply, and each component is counted once: a later branch that reaches
+    the same clauses takes the count from the cache. Every model is weighted by the product
+    of the weights of its literals, so with all weights at 1 the count is the number of models.
+*/
+struct ModelCounter<W> {
+    // Weight of each variable being true and false
+    weights: Vec<(W, W)>,
+    cache: HashMap<Vec<Vec<usize>>, W>,
+}
+
+impl<W: CountValue> ModelCounter<W> {
+    fn weight(&self, literal: Literal) -> &W {
+        let (positive, negative) = &self.weights[literal.var()];
+        if literal.is_negated() {
+            negative
+        } else {
+            positive
+        }
+    }
+
+    // Weight of a variable that no clause constrains anymore, both values are models
+    fn free_weight(&self, var: usize) -> W {
+        let (positive, negative) = &self.weights[var];
+        positive.sum(negative)
+    }
+
+    fn count(&mut self, cnf: &Cnf) -> W {
+        let mut clauses: Clauses = Vec::new();
+        for clause in cnf.clauses() {
+            let mut clause = clause.clone();
+            clause.normalize();
+            if !clause.is_tautology() {
+                clauses.push(clause.literals().to_vec());
+            }
+        }
+        let variables: HashSet<usize> = (0..cnf.num_variables()).collect();
+        match self.propagate(clauses, &variables, Vec::new()) {
+            None => W::zero(),
+            Some((clauses, factor)) => factor.product(&self.count_clauses(clauses)),
+        }
+    }
+
+    /*
+        Makes the literals of assigned true, then every unit clause, removing satisfied clauses
+        and false literals. Returns the clauses left and the weight of the variables out of them:
+        the assigned ones and those of variables that disappeared without a value.
+        None on a conflict.
+    */
+    fn propagate(
+        &self,
+        mut clauses: Clauses,
+        variables: &HashSet<usize>,
+        mut assigned: Vec<Literal>,
+    ) -> Option<(Clauses, W)> {
+        let mut values: HashMap<usize, bool> = HashMap::new();
+        loop {
+            for &literal in &assigned {
+                match values.get(&literal.var()) {
+                    Some(&value) if value == literal.is_negated() => return None,
+                    _ => {
+                        values.insert(literal.var(), !literal.is_negated());
+                    }
+                }
+            }
+            assigned.clear();
+
+            let mut simplified: Clauses = Vec::with_capacity(clauses.len());
+            for clause in clauses {
+                let mut kept: Vec<Literal> = Vec::with_capacity(clause.len());
+                let mut satisfied: bool = false;
+                for literal in clause {
+                    match values.get(&literal.var()) {
+                        Some(&value) => satisfied |= literal.value(value),
+                        None => kept.push(literal),
+                    }
+                }
+                if satisfied {
+                    continue;
+                }
+                match kept.len() {
+                    0 => return None,
+                    1 => assigned.push(kept[0]),
+                    _ => simplified.push(kept),
+                }
+            }
+            clauses = simplified;
+            if assigned.is_empty() {
+                break;
+            }
+        }
+
+        let remaining: HashSet<usize> = clauses
+            .iter()
+            .flatten()
+            .map(|literal| literal.var())
+            .collect();
+        let mut factor: W = W::one();
+        for &var in variables {
+            if let Some(&value) = values.get(&var) {
+                factor = factor.product(self.weight(Literal::new(var, !value)));
+            } else if !remaining.contains(&var) {
+                factor = factor.product(&self.free_weight(var));
+            }
+        }
+        Some((clauses, factor))
+    }
+
+    // Count of the clauses over exactly the variables they use
+    fn count_clauses(&mut self, clauses: Clauses) -> W {
+        let mut total: Option<W> = None;
+        for component in components(clauses) {
+            let count: W = self.count_component(component);
+            total = Some(match total {
+                None => count,
+                Some(total) => total.product(&count),
+            });
+        }
+        total.unwrap_or_else(W::one)
+    }
+
+    fn count_component(&mut self, mut clauses: Clauses) -> W {
+        for clause in clauses.iter_mut() {
+            clause.sort();
+        }
+        clauses.sort();
+        let key: Vec<Vec<usize>> = clauses
+            .iter()
+            .map(|clause| clause.iter().map(|literal| literal.code()).collect())
+            .collect();
+        if let Some(count) = self.cache.get(&key) {
+            return count.clone();
+        }
+
+        // Branch on the variable in the most clauses
+        let mut occurrences: HashMap<usize, usize> = HashMap::new();
+        for literal in clauses.iter().flatten() {
+            *occurrences.entry(literal.var()).or_insert(0) += 1;
+        }
+        let var: usize = *occurrences
+            .iter()
+            .max_by_key(|&(&var, &count)| (count, std::cmp::Reverse(var)))
+            .unwrap()
+            .0;
+        let variables: HashSet<usize> = occurrences.keys().copied().collect();
+
+        let mut count: W = W::zero();
+        for negated in [false, true] {
+            if let Some((rest, factor)) = self.propagate(
+                clauses.clone(),
+                &variables,
+                vec![Literal::new(var, negated)],
+            ) {
+                count = count.sum(&factor.product(&self.count_clauses(rest)));
+            }
+        }
+        self.cache.insert(key, count.clone());
+        count
+    }
+}
+
+// Groups of clauses that share no variable with each other
+fn components(clauses: Clauses) -> Vec<Clauses> {
+    let mut parent: HashMap<usize, usize> = HashMap::new();
+    fn find(parent: &mut HashMap<usize, usize>, var: usize) -> usize {
+        let next: usize = *parent.entry(var).or_insert(var);
+        if next == var {
+            return var;
+        }
+        let root: usize = find(parent, next);
+        parent.insert(var, root);
+        root
+    }
+    for clause in &clauses {
+        let first: usize = find(&mut parent, clause[0].var());
+        for literal in &clause[1..] {
+            let root: usize = find(&mut parent, literal.var());
+            parent.insert(root, first);
+        }
+    }
+
+    let mut groups: HashMap<usize, Clauses> = HashMap::new();
+    let mut order: Vec<usize> = Vec::new();
+    for clause in clauses {
+        let root: usize = find(&mut parent, clause[0].var());
+        if !groups.contains_key(&root) {
+            order.push(root);
+        }
+        groups.entry(root).or_default().push(clause);
+    }
+    order
+        .into_iter()
+        .map(|root| groups.remove(&root).unwrap())
+        .collect()
+}
+
+impl Cnf {
+    // Number of assignments of all the variables of the Cnf that satisfy every clause
+    pub fn model_count(&self) -> BigUint {
+        let mut counter: ModelCounter<BigUint> = ModelCounter {
+            weights: vec![(BigUint::one(), BigUint::one()); self.num_variables()],
+            cache: HashMap::new(),
+        };
+        counter.count(self)
+    }
+
+    /*
+        Sum over the models of the product of the weights of their literals,
+        weights[var] being the weight of var true and of var false.
+        With weights that add up to 1 for each variable it is the probability of the formula.
+    */
+    pub fn weighted_model_count(&self, weights: &[(f64, f64)]) -> f64 {
+        assert_eq!(
+            weights.len(),
+            self.num_variables(),
+            "Expected one pair of weights per variable"
+        );
+        let mut counter: ModelCounter<f64> = ModelCounter {
+            weights: weights.to_vec(),
+            cache: HashMap::new(),
+        };
+        counter.count(self)
+    }
+}
+
+impl ExpressionEvaluator<bool, BooleanOperations> {
+    /*
+        Number of true rows of the truth table of a formula, without building it.
+        The full Tseitin encoding is used, where each auxiliary variable is the value of
+        its gate, so it has exactly as many models as the formula.
+    */
+    pub fn model_count(&self, formula: &str) -> Result<BigUint, ParseError> {
+        Ok(self
+            .tseitin_transformation(formula, false)?
+            .to_cnf()
+            .model_count())
+    }
+
+    // Weights of the variables of the formula by name, (weight if true, weight if false)
+    pub fn weighted_model_count(
+        &self,
+        formula: &str,
+        weights: &HashMap<String, (f64, f64)>,
+    ) -> Result<f64, ParseError> {
+        let encoding: TseitinCnf = self.tseitin_transformation(formula, false)?;
+        let cnf: Cnf = encoding.to_cnf();
+        let mut literal_weights: Vec<(f64, f64)> = Vec::with_capacity(cnf.num_variables());
+        for name in cnf.variables() {
+            if encoding.is_auxiliary(name) {
+                // Determined by the original variables, it only has to not change the weight
+                literal_weights.push((1.0, 1.0));
+                continue;
+            }
+            match weights.get(name) {
+                Some(&weight) => literal_weights.push(weight),
+                None => {
+                    return Err(ParseError::UnboundVariable {
+                        name: name.clone(),
+                        position: None,
+                    })
+                }
+            }
+        }
+        Ok(cnf.weighted_model_count(&literal_weights))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean_operations::cnf::Clause;
+    use crate::truth_table::generate_truth_table;
+
+    #[test]
+    fn test_matches_truth_table() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        let formulas = [
+            "AB|",
+            "AB&!",
+            "AB^C=",
+            "AB>C>D|",
+            "AA!&",
+            "AB↑C↓",
+            "A1|",
+            "0",
+            "1",
+            "reset_n x12|!A&",
+            "AB|CD|&EF|&AE=&",
+        ];
+        for formula in formulas {
+            let table = generate_truth_table(formula, &mut evaluator).unwrap();
+            let expected: u64 = table.count_true() as u64;
+            assert_eq!(
+                evaluator.model_count(formula).unwrap().to_u64(),
+                Some(expected),
+                "{}",
+                formula
+            );
+            let cnf: Cnf = evaluator.cnf_formula(formula).unwrap();
+            assert_eq!(cnf.model_count().to_u64(), Some(expected), "{}", formula);
+        }
+    }
+
+    #[test]
+    fn test_large() {
+        // 100 independent clauses (x_i | y_i), 3 models each
+        let mut cnf: Cnf = Cnf::new();
+        for i in 0..100 {
+            let x: usize = cnf.add_variable(&format!("x{}", i));
+            let y: usize = cnf.add_variable(&format!("y{}", i));
+            cnf.add_clause(Clause::new(vec![
+                Literal::positive(x),
+                Literal::positive(y),
+            ]));
+        }
+        let three: BigUint = BigUint::from(3);
+        let expected: BigUint = (0..100).fold(BigUint::one(), |power, _| &power * &three);
+        assert_eq!(cnf.model_count(), expected);
+
+        // Variables in no clause double the count
+        cnf.add_variable("free");
+        assert_eq!(cnf.model_count(), &expected * &BigUint::from(2));
+
+        // x0 = x1 = ... = x59 as equivalences between neighbours has 2 models
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let names: Vec<String> = (0..60).map(|i| format!("x{}", i)).collect();
+        let pairs: Vec<String> = names
+            .windows(2)
+            .map(|pair| format!("({} = {})", pair[0], pair[1]))
+            .collect();
+        let formula: String = evaluator.infix_to_rpn(&pairs.join(" & ")).unwrap();
+        assert_eq!(evaluator.model_count(&formula).unwrap(), BigUint::from(2));
+        // And a chain of 60 xors is true for half of the 2^60 rows
+        let formula: String = evaluator.infix_to_rpn(&names.join(" ^ ")).unwrap();
+        assert_eq!(evaluator.model_count(&formula).unwrap(), BigUint::pow2(59));
+    }
+
+    #[test]
+    fn test_weighted() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let weights: HashMap<String, (f64, f64)> =
+            HashMap::from([("A".to_string(), (0.5, 0.5)), ("B".to_string(), (0.2, 0.8))]);
+        // P(A | B) = 1 - 0.5 * 0.8
+        let probability: f64 = evaluator.weighted_model_count("AB|", &weights).unwrap();
+        assert!((probability - 0.6).abs() < 1e-12);
+        let probability: f64 = evaluator.weighted_model_count("AB^", &weights).unwrap();
+        assert!((probability - 0.5).abs() < 1e-12);
+        assert_eq!(
+            evaluator.weighted_model_count("AC&", &weights),
+            Err(ParseError::UnboundVariable {
+                name: "C".to_string(),
+                position: None
+            })
+        );
+
+        // Weights of 1 give the number of models
+        let cnf: Cnf = Cnf::from_rpn("AB|B!C|&").unwrap();
+        assert_eq!(cnf.weighted_model_count(&[(1.0, 1.0); 3]), 4.0);
+        // A B C: 111 is 8, 011 is 4, 101 is 4 and 100 is 2
+        assert_eq!(cnf.weighted_model_count(&[(2.0, 1.0); 3]), 18.0);
+    }
+}
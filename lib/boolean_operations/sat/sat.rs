@@ -1,16 +1,89 @@
-use crate::aux::ExpressionEvaluator;
+use crate::aux::{Assignment, CompiledFormula, ExpressionEvaluator, ParseError};
+use crate::boolean_operations::cnf::{Cnf, TseitinCnf};
 use crate::boolean_operations::BooleanOperations;
-use crate::truth_table::generate_truth_table;
+use crate::truth_table::truth_table::fill_lanes;
+
+/*
+    Outcome of a SAT solver, with a model giving a value to every variable when there is one.
+    Unknown is only returned by incomplete solvers, like local search, that gave up.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum SatResult {
+    Sat(Assignment<bool>),
+    Unsat,
+    Unknown,
+}
+
+impl SatResult {
+    pub fn is_sat(&self) -> bool {
+        matches!(self, SatResult::Sat(_))
+    }
+
+    pub fn model(&self) -> Option<&Assignment<bool>> {
+        match self {
+            SatResult::Sat(model) => Some(model),
+            SatResult::Unsat | SatResult::Unknown => None,
+        }
+    }
+}
+
+// Any of the solvers of the module, so callers can pick one at run time
+pub trait Solver {
+    fn solve_cnf(&mut self, cnf: &Cnf) -> SatResult;
+}
+
+// A=1 B=0 ..., in the order of the variables of the model
+pub fn format_model(model: &Assignment<bool>) -> String {
+    model
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, *value as u8))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
 
 impl ExpressionEvaluator<bool, BooleanOperations> {
-    fn sat_truth_table(&mut self, formula: &str) -> bool {
-        match generate_truth_table(&formula, self) {
-            Ok(table) => table.rows.iter().any(|(_, result)| *result),
-            Err(err) => {
-                eprintln!("{}", err);
-                return false;
+    /*
+        Satisfiability of any formula: its Plaisted-Greenbaum encoding is solved with DPLL
+        and the model is projected back onto the variables of the formula, in sorted order.
+    */
+    pub fn sat(&self, formula: &str) -> Result<SatResult, ParseError> {
+        let encoding: TseitinCnf = self.tseitin_transformation(formula, true)?;
+        let cnf: Cnf = encoding.to_cnf();
+        Ok(match cnf.dpll() {
+            SatResult::Sat(model) => SatResult::Sat(
+                encoding
+                    .variables
+                    .iter()
+                    .cloned()
+                    .zip(model.values().iter().copied())
+                    .collect(),
+            ),
+            result => result,
+        })
+    }
+
+    /*
+        Tries every assignment, 64 at a time, and stops at the first block with a satisfying one.
+        Only usable with a few variables, kept as an oracle to check the solvers against.
+    */
+    pub fn sat_truth_table(&mut self, formula: &str) -> Result<bool, ParseError> {
+        let mut compiled: CompiledFormula<bool> = self.compile_formula(formula)?;
+        let num_vars: usize = compiled.variables().len();
+        let num_rows: usize = 1 << num_vars;
+        // With less than 64 rows only the low bits are rows
+        let valid: u64 = if num_rows < 64 {
+            (1 << num_rows) - 1
+        } else {
+            u64::MAX
+        };
+        let mut lanes: Vec<u64> = vec![0; num_vars];
+        for block in 0..num_rows.div_ceil(64) {
+            fill_lanes(&mut lanes, block);
+            if compiled.evaluate_lanes(&lanes) & valid != 0 {
+                return Ok(true);
             }
         }
+        Ok(false)
     }
 }
 
@@ -19,33 +92,107 @@ pub fn run_sat_truth_table() {
     let formula: &str = "AB&!";
     let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
         ExpressionEvaluator::<bool, BooleanOperations>::new();
-    let res: bool = evaluator.sat_truth_table(formula);
-
     println!("Original formula: {}", formula);
-    println!("SAT Truth Table: {}", res);
+    match evaluator.sat_truth_table(formula) {
+        Ok(res) => println!("SAT Truth Table: {}", res),
+        Err(err) => println!("SAT Truth Table: {}", err),
+    }
+    match evaluator.sat(formula) {
+        Ok(SatResult::Sat(model)) => println!("SAT DPLL: true, {}", format_model(&model)),
+        Ok(SatResult::Unsat) => println!("SAT DPLL: false"),
+        Ok(SatResult::Unknown) => println!("SAT DPLL: unknown"),
+        Err(err) => println!("SAT DPLL: {}", err),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aux::formula_variables;
+
     #[test]
     fn test_sat_truth_table() {
         let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
             ExpressionEvaluator::<bool, BooleanOperations>::new();
         let formula: &str = "AB|";
         let expected: bool = true;
-        assert_eq!(evaluator.sat_truth_table(&formula), expected);
+        assert_eq!(evaluator.sat_truth_table(formula).unwrap(), expected);
 
         let formula = "AB&";
         let expected = true;
-        assert_eq!(evaluator.sat_truth_table(&formula), expected);
+        assert_eq!(evaluator.sat_truth_table(formula).unwrap(), expected);
 
         let formula = "AA!&";
         let expected = false;
-        assert_eq!(evaluator.sat_truth_table(&formula), expected);
+        assert_eq!(evaluator.sat_truth_table(formula).unwrap(), expected);
 
         let formula = "AA^";
         let expected = false;
-        assert_eq!(evaluator.sat_truth_table(&formula), expected);
+        assert_eq!(evaluator.sat_truth_table(formula).unwrap(), expected);
+
+        assert_eq!(
+            evaluator.sat_truth_table("AB&|"),
+            Err(ParseError::OperandUnderflow {
+                operator: '|',
+                position: 3,
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_sat_matches_truth_table() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        evaluator.register_standard_connectives();
+        let formulas = [
+            "AB|",
+            "AB&",
+            "AA!&",
+            "AA^",
+            "AB=AB^&",
+            "AB>C>D|!",
+            "AB↑C↓",
+            "A1&",
+            "A0&",
+            "1",
+            "0",
+            "AB&C&D&E&A!|B!&",
+        ];
+        for formula in formulas {
+            let result: SatResult = evaluator.sat(formula).unwrap();
+            assert_eq!(
+                result.is_sat(),
+                evaluator.sat_truth_table(formula).unwrap(),
+                "{}",
+                formula
+            );
+            if let SatResult::Sat(model) = result {
+                let mut names: Vec<&str> = model.iter().map(|(name, _)| name).collect();
+                names.sort();
+                assert_eq!(names, formula_variables(formula), "{}", formula);
+                assert!(evaluator
+                    .evaluate_assignment(&evaluator.parse(formula).unwrap(), &model)
+                    .unwrap());
+            }
+        }
+        assert_eq!(
+            evaluator.sat("AB&|"),
+            Err(ParseError::OperandUnderflow {
+                operator: '|',
+                position: 3,
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_dimacs_model() {
+        let cnf: Cnf =
+            Cnf::from_dimacs("c 1 A\nc 2 reset_n\np cnf 3 3\n1 2 0\n-1 0\n-2 3 0\n").unwrap();
+        let result: SatResult = cnf.dpll();
+        assert_eq!(format_model(result.model().unwrap()), "A=0 reset_n=1 x3=1");
     }
 }
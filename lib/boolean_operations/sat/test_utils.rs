@@ -0,0 +1,90 @@
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal};
+
+/*
+    Seeded random formulas for the tests of the solvers. A small xorshift generator
+    is enough: the same seed always gives the same formula, so a failing seed can be replayed.
+*/
+pub struct RandomClauses {
+    state: u64,
+    num_variables: usize,
+}
+
+impl RandomClauses {
+    pub fn new(seed: u64, num_variables: usize) -> Self {
+        // Xorshift never leaves 0
+        RandomClauses {
+            state: seed.max(1),
+            num_variables,
+        }
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // Clause of 1 to width literals, with at most positives positive ones
+    pub fn clause(&mut self, width: usize, positives: usize) -> Clause {
+        let len: usize = 1 + self.next() as usize % width;
+        self.clause_of_len(len, positives)
+    }
+
+    pub fn clause_of_len(&mut self, len: usize, positives: usize) -> Clause {
+        let mut positive: usize = 0;
+        (0..len)
+            .map(|_| {
+                let value: u64 = self.next();
+                let negated: bool = value & 1 == 1 || positive == positives;
+                positive += !negated as usize;
+                Literal::new((value >> 1) as usize % self.num_variables, negated)
+            })
+            .collect()
+    }
+}
+
+// x0, x1, ... as names of the variables
+pub fn variables(num_variables: usize) -> Vec<String> {
+    (0..num_variables).map(|i| format!("x{}", i)).collect()
+}
+
+// Random clauses of up to width literals, with at most positives positive ones
+pub fn random_cnf(
+    seed: u64,
+    num_variables: usize,
+    num_clauses: usize,
+    width: usize,
+    positives: usize,
+) -> Cnf {
+    let mut random: RandomClauses = RandomClauses::new(seed, num_variables);
+    let mut cnf: Cnf = Cnf::with_variables(variables(num_variables));
+    for _ in 0..num_clauses {
+        cnf.add_clause(random.clause(width, positives));
+    }
+    cnf
+}
+
+// Random k-SAT, every clause has exactly k literals
+pub fn random_ksat(seed: u64, num_variables: usize, num_clauses: usize, k: usize) -> Cnf {
+    let mut random: RandomClauses = RandomClauses::new(seed, num_variables);
+    let mut cnf: Cnf = Cnf::with_variables(variables(num_variables));
+    for _ in 0..num_clauses {
+        cnf.add_clause(random.clause_of_len(k, k));
+    }
+    cnf
+}
+
+// Random k-SAT with a hidden model: clauses it falsifies are drawn again
+pub fn planted_ksat(seed: u64, num_variables: usize, num_clauses: usize, k: usize) -> Cnf {
+    let mut random: RandomClauses = RandomClauses::new(seed, num_variables);
+    let planted: Vec<bool> = (0..num_variables).map(|_| random.next() & 1 == 1).collect();
+    let mut cnf: Cnf = Cnf::with_variables(variables(num_variables));
+    while cnf.len() < num_clauses {
+        let clause: Clause = random.clause_of_len(k, k);
+        if clause.evaluate(&planted) {
+            cnf.add_clause(clause);
+        }
+    }
+    cnf
+}
@@ -0,0 +1,192 @@
+use crate::aux::expresion_eval::{ExprNode, ExpressionEvaluator};
+use crate::aux::ParseError;
+use crate::boolean_operations::cnf::clause::flatten;
+use crate::boolean_operations::cnf::{Clause, Cnf, Literal, TseitinCnf};
+use crate::boolean_operations::sat::cdcl::CdclSolver;
+use crate::boolean_operations::BooleanOperations;
+
+/*
+    Minimal unsatisfiable subset of groups of clauses, by index, or None if all of them
+    together are satisfiable. Each group gets a selector variable added to its clauses and
+    the solver only has to satisfy the groups whose selector is assumed. Starting from the
+    groups of the first failed assumptions, each group is dropped in turn: if the others are
+    still unsatisfiable it goes, along with any group the new failed assumptions leave out,
+    otherwise it belongs to every core of what is left and stays.
+*/
+fn minimal_core(variables: &[String], groups: &[Vec<Clause>]) -> Option<Vec<usize>> {
+    let mut solver: CdclSolver = CdclSolver::new(&Cnf::with_variables(variables.to_vec()));
+    let selectors: Vec<Literal> = (0..groups.len())
+        .map(|_| Literal::positive(solver.new_variable()))
+        .collect();
+    for (group, &selector) in groups.iter().zip(&selectors) {
+        for clause in group {
+            let mut clause: Clause = clause.clone();
+            clause.push(!selector);
+            solver.add_clause(&clause);
+        }
+    }
+
+    // Groups whose selectors are among the failed assumptions, in increasing order
+    let failed_groups = |solver: &CdclSolver, candidates: &[usize]| -> Vec<usize> {
+        candidates
+            .iter()
+            .copied()
+            .filter(|&group| solver.failed_assumptions().contains(&selectors[group]))
+            .collect()
+    };
+
+    let all: Vec<usize> = (0..groups.len()).collect();
+    if solver.solve_with_assumptions(&selectors).is_sat() {
+        return None;
+    }
+    let mut core: Vec<usize> = failed_groups(&solver, &all);
+    let mut i: usize = 0;
+    while i < core.len() {
+        let candidate: Vec<usize> = core
+            .iter()
+            .copied()
+            .filter(|&group| group != core[i])
+            .collect();
+        let assumptions: Vec<Literal> = candidate.iter().map(|&group| selectors[group]).collect();
+        if solver.solve_with_assumptions(&assumptions).is_sat() {
+            i += 1;
+        } else {
+            // The groups before i are needed by any core of the candidate, so they stay there
+            core = failed_groups(&solver, &candidate);
+        }
+    }
+    Some(core)
+}
+
+impl Cnf {
+    // Indices of a minimal set of clauses that is already unsatisfiable, None if the Cnf is satisfiable
+    pub fn unsat_core(&self) -> Option<Vec<usize>> {
+        let groups: Vec<Vec<Clause>> = self
+            .clauses()
+            .iter()
+            .map(|clause| vec![clause.clone()])
+            .collect();
+        minimal_core(self.variables(), &groups)
+    }
+}
+
+impl ExpressionEvaluator<bool, BooleanOperations> {
+    /*
+        Splits the formula into its top-level conjuncts, A & (B | C) & !D is A, B | C and !D,
+        and returns a minimal set of them that can't be true together, None if the formula
+        is satisfiable. Each conjunct is encoded on its own, its auxiliary variables are
+        renamed with its index so they stay apart from those of the other conjuncts.
+    */
+    pub fn unsat_core_expr(&self, node: &ExprNode<bool>) -> Option<Vec<ExprNode<bool>>> {
+        let mut conjuncts: Vec<&ExprNode<bool>> = Vec::new();
+        flatten(node, '&', &mut conjuncts);
+
+        let mut variables: Vec<String> = Vec::new();
+        self.collect_variables(node, &mut variables);
+        variables.sort();
+        variables.dedup();
+        let mut cnf: Cnf = Cnf::with_variables(variables);
+        let mut groups: Vec<Vec<Clause>> = Vec::new();
+        for (index, conjunct) in conjuncts.iter().enumerate() {
+            let encoding: TseitinCnf = self.tseitin_encode(conjunct, true);
+            let group: Vec<Clause> = encoding
+                .clauses
+                .iter()
+                .map(|clause| {
+                    clause
+                        .iter()
+                        .map(|(name, negated)| {
+                            let name: String = if encoding.is_auxiliary(name) {
+                                format!("#{}{}", index, name)
+                            } else {
+                                name.clone()
+                            };
+                            Literal::new(cnf.add_variable(&name), *negated)
+                        })
+                        .collect()
+                })
+                .collect();
+            groups.push(group);
+        }
+
+        let core: Vec<usize> = minimal_core(cnf.variables(), &groups)?;
+        Some(
+            core.into_iter()
+                .map(|index| conjuncts[index].clone())
+                .collect(),
+        )
+    }
+
+    // The conjuncts of the core written in RPN
+    pub fn unsat_core(&self, formula: &str) -> Result<Option<Vec<String>>, ParseError> {
+        let tree: ExprNode<bool> = self.parse(formula)?;
+        Ok(self
+            .unsat_core_expr(&tree)
+            .map(|core| core.iter().map(|conjunct| self.to_rpn(conjunct)).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clauses() {
+        // A, A > B, B > C, !C and an unrelated D | E
+        let cnf: Cnf = Cnf::from_rpn("AA!B|&DE|&B!C|&C!&A!E|&").unwrap();
+        let core: Vec<usize> = cnf.unsat_core().unwrap();
+        assert_eq!(core, [0, 1, 3, 4]);
+
+        // Every proper subset of the core is satisfiable
+        for removed in 0..core.len() {
+            let mut subset: Cnf = Cnf::with_variables(cnf.variables().to_vec());
+            for (i, &index) in core.iter().enumerate() {
+                if i != removed {
+                    subset.add_clause(cnf.clauses()[index].clone());
+                }
+            }
+            assert!(subset.cdcl().is_sat());
+        }
+
+        assert_eq!(Cnf::from_rpn("AB|A!|").unwrap().unsat_core(), None);
+        assert_eq!(Cnf::from_rpn("A0&B&").unwrap().unsat_core(), Some(vec![1]));
+
+        // Selectors are never taken from the variables, whatever their names
+        let cnf: Cnf =
+            Cnf::from_dimacs("c 1 #group0\nc 2 A\np cnf 2 3\n-1 0\n2 0\n-2 0\n").unwrap();
+        assert_eq!(cnf.unsat_core(), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_minimal() {
+        // Two disjoint reasons: {A, !A} and {B, B > C, !C}, the core is one of them
+        let cnf: Cnf = Cnf::from_rpn("AB&B!C|&C!&A!&").unwrap();
+        let core: Vec<usize> = cnf.unsat_core().unwrap();
+        assert!(core == [0, 4] || core == [1, 2, 3], "{:?}", core);
+    }
+
+    #[test]
+    fn test_conjuncts() {
+        let evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formula: String = evaluator
+            .infix_to_rpn("(A | B) & (C ^ D) & (A > C) & (B > C) & !C & (D = x1)")
+            .unwrap();
+        assert_eq!(
+            evaluator.unsat_core(&formula).unwrap(),
+            Some(vec![
+                "AB|".to_string(),
+                "AC>".to_string(),
+                "BC>".to_string(),
+                "C!".to_string()
+            ])
+        );
+        assert_eq!(evaluator.unsat_core("AB|C&").unwrap(), None);
+        // A formula that is not a conjunction is its own core
+        assert_eq!(
+            evaluator.unsat_core("AA^").unwrap(),
+            Some(vec!["AA^".to_string()])
+        );
+        assert!(evaluator.unsat_core("A&").is_err());
+    }
+}
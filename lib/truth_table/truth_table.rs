@@ -1,11 +1,56 @@
 use crate::aux::expresion_eval::ExpressionEvaluator;
+use crate::aux::{CompiledFormula, ParseError};
 use crate::boolean_operations::BooleanOperations;
-use std::collections::HashSet;
 
+/*
+    The results of a formula for every assignment of its variables, one bit per row.
+    Row r gives variables[j] the value of bit (n - 1 - j) of r, so the first variable
+    is the most significant and the rows come out sorted, starting with all false.
+*/
 #[derive(PartialEq, Debug)]
 pub struct TruthTable {
-    pub variables: Vec<char>,
-    pub rows: Vec<(Vec<bool>, bool)>,
+    pub variables: Vec<String>,
+    results: Vec<u64>,
+}
+
+impl TruthTable {
+    // Number of rows, 2^n for n variables
+    pub fn len(&self) -> usize {
+        1 << self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn assignment(&self, row: usize) -> Vec<bool> {
+        let num_vars: usize = self.variables.len();
+        (0..num_vars)
+            .map(|j| (row >> (num_vars - 1 - j)) & 1 == 1)
+            .collect()
+    }
+
+    pub fn result(&self, row: usize) -> bool {
+        (self.results[row / 64] >> (row % 64)) & 1 == 1
+    }
+
+    // The rows as (assignment, result) pairs, in order
+    pub fn rows(&self) -> impl Iterator<Item = (Vec<bool>, bool)> + '_ {
+        (0..self.len()).map(|row| (self.assignment(row), self.result(row)))
+    }
+
+    // Number of rows where the formula is true
+    pub fn count_true(&self) -> usize {
+        self.results
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    // The results packed 64 rows per word, bit r % 64 of word r / 64 is row r
+    pub fn results(&self) -> &[u64] {
+        &self.results
+    }
 }
 
 pub fn print_truth_table(table: &TruthTable) {
@@ -15,72 +60,88 @@ pub fn print_truth_table(table: &TruthTable) {
     }
     println!("= |");
 
-    println!("{}", "-".repeat(4 * (table.variables.len() + 1)));
+    let width: usize = table
+        .variables
+        .iter()
+        .map(|var| var.len() + 3)
+        .sum::<usize>()
+        + 4;
+    println!("{}", "-".repeat(width));
 
-    for (assignment, result) in &table.rows {
+    for (assignment, result) in table.rows() {
         print!("| ");
-        for &value in assignment {
-            print!("{} | ", if value { 1 } else { 0 });
+        // Values are padded to the width of their variable name
+        for (var, &value) in table.variables.iter().zip(&assignment) {
+            print!(
+                "{:<width$} | ",
+                if value { 1 } else { 0 },
+                width = var.len()
+            );
         }
-        println!("{} |", if *result { 1 } else { 0 });
+        println!("{} |", if result { 1 } else { 0 });
+    }
+}
+
+// Bit k of a row number repeats with period 2^(k + 1), these are the first six as 64 bit words
+const LANE_PATTERNS: [u64; 6] = [
+    0xAAAA_AAAA_AAAA_AAAA,
+    0xCCCC_CCCC_CCCC_CCCC,
+    0xF0F0_F0F0_F0F0_F0F0,
+    0xFF00_FF00_FF00_FF00,
+    0xFFFF_0000_FFFF_0000,
+    0xFFFF_FFFF_0000_0000,
+];
+
+/*
+    Fills lanes with the values of the variables for rows 64 * block to 64 * block + 63.
+    Variable j takes bit k = n - 1 - j of the row number. When k < 6 that bit changes
+    inside the word and follows one of the patterns, otherwise it is the same for the
+    whole word and comes from the block number.
+
+    n = 3, rows 0 to 7:
+    row	A	B	C
+    0	0	0	0
+    1	0	0	1
+    2	0	1	0
+    3	0	1	1
+    4	1	0	0
+    ...
+    C is pattern 0 (0b10101010), B pattern 1 (0b11001100) and A pattern 2 (0b11110000)
+*/
+pub(crate) fn fill_lanes(lanes: &mut [u64], block: usize) {
+    let num_vars: usize = lanes.len();
+    for (j, lane) in lanes.iter_mut().enumerate() {
+        let k: usize = num_vars - 1 - j;
+        *lane = if k < 6 {
+            LANE_PATTERNS[k]
+        } else if (block >> (k - 6)) & 1 == 1 {
+            u64::MAX
+        } else {
+            0
+        };
     }
 }
 
 pub fn generate_truth_table(
     formula: &str,
     evaluator: &mut ExpressionEvaluator<bool, BooleanOperations>,
-) -> Result<TruthTable, String> {
-    let mut variables: Vec<char> = formula
-        .chars()
-        .filter(|c| c.is_ascii_uppercase())
-        .collect::<HashSet<_>>()
-        .into_iter()
-        .collect();
-
-    variables.sort();
-
-    let num_vars: usize = variables.len();
-    let mut rows: Vec<(Vec<bool>, bool)> = Vec::new();
-    let permutations: usize = 1 << num_vars;
-
-    // Generate all 2^n combinations
-    for i in 0..permutations {
-        let mut assignment: Vec<bool> = vec![false; num_vars];
-        let mut formula_instance: String = formula.to_string();
-        for (j, var) in variables.iter().enumerate() {
-            /*
-            Each bit of i represents the value of a variable in the formula.
-            For example if i = 3, thats 011 in binary,
-            so with three variables A, B, C, the assignment would be [false, true, true]
-            we move the bits to the right by j and check if the LSB is 1 to know if the variable is true or false.
-
-            0	000	0	0	0
-            1	001	1	0	0
-            2	010	0	1	0
-            3	011	1	1	0
-            4	100	0	0	1
-            5	101	1	0	1
-            6	110	0	1	1
-            7	111	1	1	1
-            */
-            let value = ((i >> j) & 1) == 1;
-            assignment[j] = value;
-            formula_instance = formula_instance.replace(*var, if value { "1" } else { "0" });
-        }
+) -> Result<TruthTable, ParseError> {
+    // The formula is parsed and compiled once, then evaluated 64 rows at a time
+    let mut compiled: CompiledFormula<bool> = evaluator.compile_formula(formula)?;
+    let variables: Vec<String> = compiled.variables().to_vec();
 
-        // Evaluate the formula with current variable assignments
-        match evaluator.evaluate(&formula_instance, None) {
-            Ok(result) => rows.push((assignment, result)),
-            Err(err) => {
-                return Err(format!(
-                    "Error evaluating formula '{}': {}",
-                    formula_instance, err
-                ))
-            }
-        }
+    let num_rows: usize = 1 << variables.len();
+    let mut lanes: Vec<u64> = vec![0; variables.len()];
+    let mut results: Vec<u64> = Vec::with_capacity(num_rows.div_ceil(64));
+    for block in 0..num_rows.div_ceil(64) {
+        fill_lanes(&mut lanes, block);
+        results.push(compiled.evaluate_lanes(&lanes));
     }
-    rows.sort();
-    Ok(TruthTable { variables, rows })
+    // With less than 64 rows the bits past the last one are not rows
+    if num_rows < 64 {
+        results[0] &= (1 << num_rows) - 1;
+    }
+    Ok(TruthTable { variables, results })
 }
 
 pub fn run_truth_table() {
@@ -115,7 +176,7 @@ mod tests {
             (vec![true, true], true),
         ];
 
-        assert_eq!(truth_table.rows, expected);
+        assert_eq!(truth_table.rows().collect::<Vec<_>>(), expected);
     }
 
     #[test]
@@ -132,7 +193,7 @@ mod tests {
             (vec![true, true], true),
         ];
 
-        assert_eq!(truth_table.rows, expected);
+        assert_eq!(truth_table.rows().collect::<Vec<_>>(), expected);
     }
 
     #[test]
@@ -144,7 +205,7 @@ mod tests {
 
         let expected: Vec<(Vec<bool>, bool)> = vec![(vec![false], true), (vec![true], false)];
 
-        assert_eq!(truth_table.rows, expected);
+        assert_eq!(truth_table.rows().collect::<Vec<_>>(), expected);
     }
 
     #[test]
@@ -165,7 +226,65 @@ mod tests {
             (vec![true, true, true], true),
         ];
 
-        assert_eq!(truth_table.rows, expected);
+        assert_eq!(truth_table.rows().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let formula: &str = "reset_n x12&A|";
+        let truth_table: TruthTable = generate_truth_table(formula, &mut evaluator).unwrap();
+
+        assert_eq!(truth_table.variables, vec!["A", "reset_n", "x12"]);
+        assert_eq!(
+            truth_table.results(),
+            generate_truth_table("BC&A|", &mut evaluator)
+                .unwrap()
+                .results()
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        let truth_table: TruthTable = generate_truth_table("A1&0|", &mut evaluator).unwrap();
+        assert_eq!(
+            truth_table.rows().collect::<Vec<_>>(),
+            vec![(vec![false], false), (vec![true], true)]
+        );
+
+        let truth_table: TruthTable = generate_truth_table("10>", &mut evaluator).unwrap();
+        assert_eq!(
+            truth_table.rows().collect::<Vec<_>>(),
+            vec![(vec![], false)]
+        );
+    }
+
+    #[test]
+    fn test_many_rows() {
+        let mut evaluator: ExpressionEvaluator<bool, BooleanOperations> =
+            ExpressionEvaluator::<bool, BooleanOperations>::new();
+        // 2^20 rows, true only when every variable is
+        let names: Vec<String> = (0..20).map(|i| format!("x{:02}", i)).collect();
+        let formula: String = evaluator.infix_to_rpn(&names.join(" & ")).unwrap();
+        let truth_table: TruthTable = generate_truth_table(&formula, &mut evaluator).unwrap();
+        assert_eq!(truth_table.len(), 1 << 20);
+        assert_eq!(truth_table.count_true(), 1);
+        assert!(truth_table.result((1 << 20) - 1));
+
+        // Rows past the first word and the ones that use every pattern
+        let truth_table: TruthTable =
+            generate_truth_table("ABCDEFGH^^^^^^^", &mut evaluator).unwrap();
+        assert_eq!(truth_table.count_true(), 128);
+        for (row, (assignment, result)) in truth_table.rows().enumerate() {
+            assert_eq!(assignment, truth_table.assignment(row));
+            assert_eq!(
+                result,
+                assignment.iter().filter(|&&value| value).count() % 2 == 1
+            );
+        }
     }
 
     #[test]
@@ -174,7 +293,14 @@ mod tests {
             ExpressionEvaluator::<bool, BooleanOperations>::new();
         let formula: &str = "AB!!";
 
-        let result: Result<TruthTable, String> = generate_truth_table(formula, &mut evaluator);
-        assert!(result.is_err());
+        let result: Result<TruthTable, ParseError> = generate_truth_table(formula, &mut evaluator);
+        assert_eq!(
+            result,
+            Err(ParseError::LeftoverOperands {
+                count: 2,
+                position: 1,
+                snippet: "B!!".to_string()
+            })
+        );
     }
 }